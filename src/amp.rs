@@ -1,17 +1,20 @@
 pub mod parse;
 
 use crate::{
-    rubr::{naft, strange},
+    rubr::{fuzz, naft, strange},
     util,
 };
 
 use tracing::info;
+use unicode_normalization::UnicodeNormalization;
 
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
 pub struct Path {
     pub is_definition: bool,
     pub is_absolute: bool,
     pub parts: Vec<Part>,
+    // A trailing `&` indicates the Node depends on the item defining this Path
+    pub is_dependency: bool,
 }
 
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
@@ -19,13 +22,30 @@ pub struct Paths {
     pub data: Vec<Path>,
 }
 
+// Result of resolving a relative Path against a set of definitions
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Resolution {
+    Unresolved,
+    Unique(Path),
+    // Indices into Paths.data of the competing definitions
+    Ambiguous(Vec<usize>),
+}
+
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
 pub enum Part {
     Tag(Tag),
     Status(Status),
     Date(Date),
     Duration(Duration),
+    Estimate(Estimate),
     Prio(Prio),
+    Scope(Scope),
+    Risk(Risk),
+    Effort(Effort),
+    KeyValue(KeyValue),
+    Id(Id),
+    // `::` skips any number of intermediate Parts
+    Gap,
 }
 
 #[derive(PartialEq, Eq, Debug, Clone, Default, PartialOrd, Ord)]
@@ -34,11 +54,34 @@ pub struct Tag {
     pub exclusive: bool,
 }
 
-#[derive(PartialEq, Eq, Debug, Clone, PartialOrd, Ord)]
-pub enum Status {
-    Todo,
-    Wip,
-    Done,
+// Attribute like `owner=alice`. The value is absent for `owner=`
+#[derive(Debug, Eq, PartialEq, Clone, PartialOrd, Ord)]
+pub struct KeyValue(pub String, pub Option<String>);
+
+// Stable item identifier like `#12`
+#[derive(Debug, Eq, PartialEq, Clone, Copy, PartialOrd, Ord, Hash)]
+pub struct Id(pub u32);
+
+// A Status is identified by its name, its semantics are copied from the corresponding StatusDef
+#[derive(PartialEq, Eq, Debug, Clone, Default, PartialOrd, Ord)]
+pub struct Status {
+    pub name: String,
+    pub is_open: bool,
+}
+
+// Defines a Status via config or via a `&!~status:NAME[:open|:closed[:TO]*]` def
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct StatusDef {
+    pub name: String,
+    // An open Status still requires work, a closed Status does not
+    pub is_open: bool,
+    // Statuses this Status can move to. When empty, all transitions are allowed.
+    pub transitions: Vec<String>,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Statuses {
+    pub data: Vec<StatusDef>,
 }
 
 #[derive(PartialEq, Eq, Debug, Clone, Default, PartialOrd, Ord)]
@@ -53,35 +96,547 @@ pub struct Duration {
     minutes: u32,
 }
 
+// PERT estimate `optimistic..pessimistic` or `optimistic..likely..pessimistic`
+#[derive(PartialEq, Eq, Debug, Clone, Default, PartialOrd, Ord)]
+pub struct Estimate {
+    pub optimistic: Duration,
+    pub likely: Option<Duration>,
+    pub pessimistic: Duration,
+}
+
+// Work calendar used to convert between weeks, days and hours of work.
+// Durations are always expressed in this team Calendar, members that work less, eg part-timers,
+// get their own Calendar via Env.workers to determine how long their work takes.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Calendar {
+    pub hours_per_day: u32,
+    pub days_per_week: u32,
+    pub holidays: Vec<Date>,
+}
+
+// Environment used to interpret typed Parts, passed explicitly to resolution and matching
+// - `today` is the reference Date for relative Dates like `tomorrow` or `+3d`
+// - `calendar` converts Durations like `1w` or `2d` into minutes of work, and back
+// - `statuses` are the known Statuses: the built-in ones, extended via config and `~status` defs
+// - `workers` are the Calendars of members that differ from `calendar`, by the name used in `&worker=NAME`
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Env {
+    pub today: Date,
+    pub calendar: Calendar,
+    pub statuses: Statuses,
+    pub workers: std::collections::BTreeMap<String, Calendar>,
+}
+
 #[derive(PartialEq, Eq, Debug, Clone, Default, PartialOrd, Ord)]
 pub struct Prio {
     pub major: u32,
     pub minor: u32,
 }
 
+// MoSCoW scope
+#[derive(PartialEq, Eq, Debug, Clone, Copy, PartialOrd, Ord)]
+pub enum Scope {
+    Must,
+    Should,
+    Could,
+    Deferred,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, PartialOrd, Ord)]
+pub enum Risk {
+    Low,
+    Medium,
+    High,
+}
+
+// T-shirt size
+#[derive(PartialEq, Eq, Debug, Clone, Copy, PartialOrd, Ord)]
+pub enum Effort {
+    XS,
+    S,
+    M,
+    L,
+    XL,
+}
+
 impl Status {
     pub fn new() -> Status {
         Default::default()
     }
 }
 
-impl Default for Status {
+impl StatusDef {
+    pub fn new(name: &str, is_open: bool, transitions: &[&str]) -> StatusDef {
+        StatusDef {
+            name: name.into(),
+            is_open,
+            transitions: transitions.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+}
+
+// The built-in Statuses
+impl Default for Statuses {
+    fn default() -> Self {
+        let open = ["inbox", "todo", "next", "wip", "wait", "blocked"];
+        let closed = ["done", "cancelled"];
+        let mut data = Vec::new();
+        data.extend(open.iter().map(|name| StatusDef::new(name, true, &[])));
+        data.extend(closed.iter().map(|name| StatusDef::new(name, false, &[])));
+        Statuses { data }
+    }
+}
+
+impl Statuses {
+    // Fails when `s` is not a known Status
+    pub fn parse(&self, s: &str) -> util::Result<Status> {
+        match self.get(s) {
+            Some(def) => Ok(Status {
+                name: def.name.clone(),
+                is_open: def.is_open,
+            }),
+            None => Err(util::Error::create("This is not a valid status")),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&StatusDef> {
+        self.data.iter().find(|def| is_same_text(&def.name, name))
+    }
+
+    // Transitions to Statuses that are not known, as `(from, to)`
+    pub fn unknown_transitions(&self) -> Vec<(&str, &str)> {
+        self.data
+            .iter()
+            .flat_map(|def| {
+                def.transitions
+                    .iter()
+                    .filter(|to| self.get(to).is_none())
+                    .map(|to| (def.name.as_str(), to.as_str()))
+            })
+            .collect()
+    }
+
+    // Replaces an existing StatusDef with the same name
+    pub fn insert(&mut self, def: StatusDef) {
+        if let Some(existing) = self
+            .data
+            .iter_mut()
+            .find(|d| is_same_text(&d.name, &def.name))
+        {
+            *existing = def;
+        } else {
+            self.data.push(def);
+        }
+    }
+}
+
+impl Default for Calendar {
+    fn default() -> Self {
+        Calendar {
+            hours_per_day: 8,
+            days_per_week: 5,
+            holidays: Vec::new(),
+        }
+    }
+}
+
+impl Calendar {
+    pub fn minutes_per_day(&self) -> u32 {
+        self.hours_per_day * 60
+    }
+    pub fn minutes_per_week(&self) -> u32 {
+        self.days_per_week * self.minutes_per_day()
+    }
+
+    // The first `days_per_week` weekdays, starting from Monday, are working days
+    pub fn is_workday(&self, date: &Date) -> bool {
+        (date.weekday() as u32) < self.days_per_week && !self.holidays.contains(date)
+    }
+
+    // The Date on which `duration` of work, started at the beginning of `from`, is finished
+    pub fn add_duration(&self, from: &Date, duration: &Duration) -> Date {
+        let mut date = from.clone();
+        let mut minutes = duration.minutes;
+        loop {
+            if self.is_workday(&date) {
+                if minutes <= self.minutes_per_day() {
+                    return date;
+                }
+                minutes -= self.minutes_per_day();
+            }
+            date = date.add_days(1);
+        }
+    }
+}
+
+// Uses the system clock for `today`, the default Calendar and the built-in Statuses
+impl Default for Env {
     fn default() -> Self {
-        Status::Todo
+        Env {
+            today: Date::today(),
+            calendar: Calendar::default(),
+            statuses: Statuses::default(),
+            workers: Default::default(),
+        }
+    }
+}
+
+impl Env {
+    // The Calendar of `worker`, or the team Calendar when `worker` has none
+    pub fn worker_calendar(&self, worker: Option<&str>) -> &Calendar {
+        worker
+            .and_then(|worker| {
+                self.workers
+                    .iter()
+                    .find_map(|(name, calendar)| is_same_text(name, worker).then_some(calendar))
+            })
+            .unwrap_or(&self.calendar)
+    }
+}
+
+impl Estimate {
+    // A single-point estimate without spread
+    pub fn point(duration: &Duration) -> Estimate {
+        Estimate {
+            optimistic: duration.clone(),
+            likely: None,
+            pessimistic: duration.clone(),
+        }
+    }
+
+    // When the most-likely value is absent, the middle of the range is used
+    fn likely_minutes(&self) -> u32 {
+        match &self.likely {
+            Some(likely) => likely.minutes,
+            None => (self.optimistic.minutes + self.pessimistic.minutes) / 2,
+        }
+    }
+
+    // (optimistic + 4*likely + pessimistic) / 6
+    pub fn expected(&self) -> Duration {
+        let sum = self.optimistic.minutes + 4 * self.likely_minutes() + self.pessimistic.minutes;
+        Duration {
+            minutes: (sum + 3) / 6,
+        }
+    }
+
+    // ((pessimistic - optimistic) / 6)^2, in minutes^2
+    pub fn variance(&self) -> f64 {
+        let spread = (self.pessimistic.minutes - self.optimistic.minutes) as f64 / 6.0;
+        spread * spread
+    }
+
+    pub fn parse(s: &str, calendar: &Calendar) -> util::Result<Estimate> {
+        let durations = s
+            .split("..")
+            .map(|part| Duration::parse(part, calendar))
+            .collect::<util::Result<Vec<_>>>()?;
+        let estimate = match durations.as_slice() {
+            [optimistic, pessimistic] => Estimate {
+                optimistic: optimistic.clone(),
+                likely: None,
+                pessimistic: pessimistic.clone(),
+            },
+            [optimistic, likely, pessimistic] => Estimate {
+                optimistic: optimistic.clone(),
+                likely: Some(likely.clone()),
+                pessimistic: pessimistic.clone(),
+            },
+            _ => {
+                return Err(util::Error::create(format!(
+                    "Estimate '{s}' should be 'optimistic..pessimistic' or 'optimistic..likely..pessimistic'"
+                )))
+            }
+        };
+
+        let likely = estimate.likely_minutes();
+        if !(estimate.optimistic.minutes <= likely && likely <= estimate.pessimistic.minutes) {
+            return Err(util::Error::create(format!(
+                "Estimate '{s}' should be ordered from optimistic to pessimistic"
+            )));
+        }
+
+        Ok(estimate)
+    }
+
+    pub fn to_string_with(&self, calendar: &Calendar) -> String {
+        let mut s = self.optimistic.to_string_with(calendar);
+        if self.likely.is_none() && self.optimistic == self.pessimistic {
+            return s;
+        }
+        if let Some(likely) = &self.likely {
+            s.push_str("..");
+            s.push_str(&likely.to_string_with(calendar));
+        }
+        s.push_str("..");
+        s.push_str(&self.pessimistic.to_string_with(calendar));
+        s
+    }
+}
+
+impl std::ops::AddAssign<&Duration> for Duration {
+    fn add_assign(&mut self, rhs: &Duration) {
+        self.minutes += rhs.minutes;
     }
 }
 
 impl Duration {
     pub fn new(weeks: u32, days: u32, hours: u32, minutes: u32) -> Duration {
-        let minutes = minutes + (hours + (days + weeks * 5) * 8) * 60;
+        Self::from_calendar(weeks, days, hours, minutes, &Calendar::default())
+    }
+    pub fn from_calendar(
+        weeks: u32,
+        days: u32,
+        hours: u32,
+        minutes: u32,
+        calendar: &Calendar,
+    ) -> Duration {
+        let minutes = minutes
+            + hours * 60
+            + days * calendar.minutes_per_day()
+            + weeks * calendar.minutes_per_week();
         Duration { minutes }
     }
+
+    pub fn minutes(&self) -> u32 {
+        self.minutes
+    }
+
+    pub fn parse(s: &str, calendar: &Calendar) -> util::Result<Duration> {
+        let mut minutes = 0_u32;
+
+        {
+            let mut strange = strange::Strange::new(s);
+            while !strange.is_empty() {
+                if let Some(v) = strange.read_number::<u32>() {
+                    if !strange.read_char_when(|ch| {
+                        match ch {
+                            'w' => minutes += v * calendar.minutes_per_week(),
+                            'd' => minutes += v * calendar.minutes_per_day(),
+                            'h' => minutes += v * 60,
+                            'm' => minutes += v,
+                            _ => return false,
+                        }
+                        true
+                    }) {
+                        return Err(util::Error::create(format!(
+                            "Unexpected unit found in Duration '{}'",
+                            s
+                        )));
+                    }
+                } else {
+                    return Err(util::Error::create(format!(
+                        "Could not read number from Duration '{}'",
+                        s
+                    )));
+                }
+            }
+        }
+
+        Ok(Duration { minutes })
+    }
+
+    pub fn to_string_with(&self, calendar: &Calendar) -> String {
+        let mut s = String::new();
+        let mut m = self.minutes;
+        let mut cb = |div: u32, suffix: char| {
+            let n = m / div;
+            if n > 0 {
+                s.push_str(&format!("{n}{suffix}"));
+                m -= n * div;
+            }
+        };
+        cb(calendar.minutes_per_week(), 'w');
+        cb(calendar.minutes_per_day(), 'd');
+        cb(60, 'h');
+        cb(1, 'm');
+        if s.is_empty() {
+            s.push_str("0m");
+        }
+        s
+    }
 }
 
+const WEEKDAYS: [&str; 7] = [
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+];
+
 impl Date {
     pub fn new(year: u16, month: u8, day: u8) -> Date {
         Date { year, month, day }
     }
+
+    // Current Date according to the system clock
+    pub fn today() -> Date {
+        let secs = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Date::from_days((secs / (24 * 60 * 60)) as i64)
+    }
+    pub fn is_leap_year(year: u16) -> bool {
+        (year.is_multiple_of(4) && !year.is_multiple_of(100)) || year.is_multiple_of(400)
+    }
+    pub fn days_in_month(year: u16, month: u8) -> u8 {
+        match month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if Self::is_leap_year(year) => 29,
+            2 => 28,
+            _ => 0,
+        }
+    }
+    pub fn is_valid(&self) -> bool {
+        (1..=12).contains(&self.month)
+            && self.day >= 1
+            && self.day <= Self::days_in_month(self.year, self.month)
+    }
+
+    // Number of days since 1970-01-01
+    pub fn to_days(&self) -> i64 {
+        let (month, day) = (self.month as i64, self.day as i64);
+        let year = self.year as i64 - if month <= 2 { 1 } else { 0 };
+        let era = year.div_euclid(400);
+        let yoe = year - era * 400;
+        let doy = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146097 + doe - 719468
+    }
+    pub fn from_days(days: i64) -> Date {
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+        Date::new(year as u16, month as u8, day as u8)
+    }
+
+    // 0 for Monday, 6 for Sunday
+    pub fn weekday(&self) -> u8 {
+        (self.to_days() + 3).rem_euclid(7) as u8
+    }
+
+    pub fn add_days(&self, days: i64) -> Date {
+        Date::from_days(self.to_days() + days)
+    }
+    // Positive when `self` comes after `rhs`
+    pub fn days_since(&self, rhs: &Date) -> i64 {
+        self.to_days() - rhs.to_days()
+    }
+
+    // Parses an absolute date (`2024-10-02`) or an expression relative to `today`:
+    // - `today`, `tomorrow` and `yesterday`
+    // - a full weekday name (`friday`), resolving to its first occurrence from `today` on
+    // - `next-` followed by a full weekday name (`next-friday`), resolving to its first occurrence after `today`
+    // - an offset in days and weeks (`+3d`, `-1w2d`)
+    pub fn parse(s: &str, today: &Date) -> util::Result<Date> {
+        let lower = s.to_lowercase();
+        match lower.as_str() {
+            "today" => return Ok(today.clone()),
+            "tomorrow" => return Ok(today.add_days(1)),
+            "yesterday" => return Ok(today.add_days(-1)),
+            _ => {}
+        }
+
+        // Abbreviations like `mon` are not accepted, they are too likely to be meant as a plain Tag
+        let (name, skip) = match lower.strip_prefix("next-") {
+            Some(name) => (name, 1),
+            None => (lower.as_str(), 0),
+        };
+        if let Some(weekday) = WEEKDAYS.iter().position(|weekday| *weekday == name) {
+            let diff = (weekday as i64 - today.weekday() as i64 - skip).rem_euclid(7) + skip;
+            return Ok(today.add_days(diff));
+        }
+
+        let mut strange = strange::Strange::new(&lower);
+        if let Some(sign) = strange.try_read_char_when(|ch| ch == '+' || ch == '-') {
+            let mut days = 0_i64;
+            while !strange.is_empty() {
+                let Some(v) = strange.read_number::<u32>() else {
+                    return Err(util::Error::create(format!(
+                        "Could not read number from relative Date '{s}'"
+                    )));
+                };
+                let unit = match strange.try_read_char() {
+                    Some('d') => 1,
+                    Some('w') => 7,
+                    _ => {
+                        return Err(util::Error::create(format!(
+                            "Expected unit 'd' or 'w' in relative Date '{s}'"
+                        )))
+                    }
+                };
+                days += v as i64 * unit;
+            }
+            if sign == '-' {
+                days = -days;
+            }
+            return Ok(today.add_days(days));
+        }
+
+        Self::parse_absolute(s)
+    }
+
+    // Checks if `s` is an absolute or relative Date, which does not depend on the reference Date
+    pub fn is_date(s: &str) -> bool {
+        Date::parse(s, &Date::from_days(0)).is_ok()
+    }
+
+    // Parses an absolute date like `2024-10-02`, relative expressions are not accepted
+    pub fn parse_absolute(s: &str) -> util::Result<Date> {
+        let mut strange = strange::Strange::new(s);
+
+        let year;
+        let month;
+        let day;
+
+        if let Some(s) = strange.read_decimals(4) {
+            year = strange::Strange::new(s).read_number::<u16>().unwrap();
+        } else {
+            return Err(util::Error::create("Could not read year for Date"));
+        }
+
+        strange.read_char_if('-');
+
+        if let Some(s) = strange.read_decimals(2) {
+            month = strange::Strange::new(s).read_number::<u8>().unwrap();
+        } else {
+            return Err(util::Error::create("Could not read month for Date"));
+        }
+
+        strange.read_char_if('-');
+
+        if let Some(s) = strange.read_decimals(2) {
+            day = strange::Strange::new(s).read_number::<u8>().unwrap();
+        } else {
+            return Err(util::Error::create("Could not read day for Date"));
+        }
+
+        if !strange.is_empty() {
+            return Err(util::Error::create(format!(
+                "Date '{s}' cannot contain additional data"
+            )));
+        }
+
+        let date = Date { year, month, day };
+        if !date.is_valid() {
+            return Err(util::Error::create(format!(
+                "Date '{s}' is not a valid calendar day"
+            )));
+        }
+
+        Ok(date)
+    }
 }
 
 impl Prio {
@@ -90,6 +645,85 @@ impl Prio {
     }
 }
 
+// The first name is used for Display, the others are aliases
+impl Scope {
+    const NAMES: [(&'static str, Scope); 5] = [
+        ("must", Scope::Must),
+        ("should", Scope::Should),
+        ("could", Scope::Could),
+        ("deferred", Scope::Deferred),
+        ("wont", Scope::Deferred),
+    ];
+}
+impl Risk {
+    const NAMES: [(&'static str, Risk); 3] = [
+        ("low", Risk::Low),
+        ("medium", Risk::Medium),
+        ("high", Risk::High),
+    ];
+}
+impl Effort {
+    const NAMES: [(&'static str, Effort); 8] = [
+        ("xs", Effort::XS),
+        ("s", Effort::S),
+        ("m", Effort::M),
+        ("l", Effort::L),
+        ("xl", Effort::XL),
+        // `medium` is reserved for Risk, keeping `&medium` unambiguous
+        ("small", Effort::S),
+        ("med", Effort::M),
+        ("large", Effort::L),
+    ];
+}
+
+fn name_of<T: PartialEq>(names: &[(&'static str, T)], value: &T) -> &'static str {
+    names
+        .iter()
+        .find_map(|(name, v)| (v == value).then_some(*name))
+        .unwrap_or("?")
+}
+fn value_of<T: Copy>(names: &[(&'static str, T)], s: &str, what: &str) -> util::Result<T> {
+    names
+        .iter()
+        .find_map(|(name, v)| is_same_text(name, s).then_some(*v))
+        .ok_or_else(|| util::Error::create(format!("Unknown {what} '{s}'")))
+}
+
+impl TryFrom<&str> for Scope {
+    type Error = util::ErrorType;
+    fn try_from(s: &str) -> util::Result<Scope> {
+        value_of(&Scope::NAMES, s, "Scope")
+    }
+}
+impl TryFrom<&str> for Risk {
+    type Error = util::ErrorType;
+    fn try_from(s: &str) -> util::Result<Risk> {
+        value_of(&Risk::NAMES, s, "Risk")
+    }
+}
+impl TryFrom<&str> for Effort {
+    type Error = util::ErrorType;
+    fn try_from(s: &str) -> util::Result<Effort> {
+        value_of(&Effort::NAMES, s, "Effort")
+    }
+}
+
+impl std::fmt::Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", name_of(&Scope::NAMES, self))
+    }
+}
+impl std::fmt::Display for Risk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", name_of(&Risk::NAMES, self))
+    }
+}
+impl std::fmt::Display for Effort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", name_of(&Effort::NAMES, self))
+    }
+}
+
 impl Paths {
     pub fn new() -> Paths {
         Default::default()
@@ -116,16 +750,56 @@ impl Paths {
         Ok(())
     }
 
-    pub fn matches_with(&self, needle: &Path) -> bool {
+    pub fn matches_with(&self, needle: &Path, env: &Env) -> bool {
+        self.data
+            .iter()
+            .any(|path| path.matches_with(needle, false, env))
+    }
+
+    // Tags from `needle` also match with Tags that are a few edits away
+    pub fn fuzzy_matches_with(&self, needle: &Path, env: &Env) -> bool {
         self.data
             .iter()
-            .any(|path| path.matches_with(needle, false))
+            .any(|path| path.fuzzy_matches_with(needle, env))
     }
 
-    pub fn resolve(&self, rel: &Path) -> Option<Path> {
+    // Collects all definitions that can create a Path from `rel`, together with their index and
+    // the amount of leading definition Parts that were skipped
+    pub fn candidates(&self, rel: &Path, env: &Env) -> Vec<(usize, Path, usize)> {
         self.data
             .iter()
-            .find_map(|path| path.create_from_template(rel))
+            .enumerate()
+            .filter_map(|(ix, def)| {
+                def.create_from_template(rel, env)
+                    .map(|(path, skipped)| (ix, path, skipped))
+            })
+            .collect()
+    }
+
+    // A partial match, where leading definition Parts are skipped, is only accepted when it is unique.
+    // A full match takes precedence over partial matches.
+    pub fn resolve(&self, rel: &Path, env: &Env) -> Resolution {
+        let candidates = self.candidates(rel, env);
+
+        let is_unique = |candidates: &[&(usize, Path, usize)]| {
+            candidates
+                .split_first()
+                .filter(|(first, rest)| rest.iter().all(|c| c.1 == first.1))
+                .map(|(first, _)| first.1.clone())
+        };
+
+        let all = candidates.iter().collect::<Vec<_>>();
+        if all.is_empty() {
+            return Resolution::Unresolved;
+        }
+        if let Some(path) = is_unique(&all) {
+            return Resolution::Unique(path);
+        }
+        let full = all.iter().filter(|c| c.2 == 0).copied().collect::<Vec<_>>();
+        if let Some(path) = is_unique(&full) {
+            return Resolution::Unique(path);
+        }
+        Resolution::Ambiguous(all.iter().map(|c| c.0).collect())
     }
 }
 
@@ -142,9 +816,22 @@ impl naft::ToNaft for Paths {
 
 impl std::fmt::Display for Paths {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write_with(f, &Calendar::default())
+    }
+}
+
+impl Paths {
+    // Durations are formatted according to `calendar`
+    pub fn to_string_with(&self, calendar: &Calendar) -> String {
+        let mut s = String::new();
+        let _ = self.write_with(&mut s, calendar);
+        s
+    }
+    fn write_with(&self, w: &mut impl std::fmt::Write, calendar: &Calendar) -> std::fmt::Result {
         let mut prefix = "";
         for path in &self.data {
-            write!(f, "{prefix}{path}")?;
+            write!(w, "{prefix}")?;
+            path.write_with(w, calendar)?;
             prefix = " ";
         }
         Ok(())
@@ -156,12 +843,18 @@ impl naft::ToNaft for Path {
         b.node(&"Path")?;
         b.attr("def", &self.is_definition)?;
         b.attr("abs", &self.is_absolute)?;
+        if self.is_dependency {
+            b.attr("dep", &self.is_dependency)?;
+        }
         for part in &self.parts {
             match part {
                 Part::Tag(tag) => {
                     b.attr("text", &tag.text)?;
                     b.attr("exclusive", &tag.exclusive)?;
                 }
+                Part::KeyValue(kv) => b.attr("kv", kv)?,
+                Part::Id(id) => b.attr("id", id)?,
+                Part::Gap => b.attr("gap", &true)?,
                 _ => b.attr("?", &"?")?,
             }
         }
@@ -174,7 +867,8 @@ impl Path {
         Path {
             is_definition,
             is_absolute,
-            parts: parts.iter().map(|s| Part::Tag(Tag::from(*s))).collect(),
+            parts: parts.iter().map(|s| Part::from(*s)).collect(),
+            is_dependency: false,
         }
     }
 
@@ -219,6 +913,85 @@ impl Path {
         }
     }
 
+    // A Status definition is a def starting with `~status`, eg `&!~status:review:open:done`
+    pub fn is_status_def(&self) -> bool {
+        self.is_definition
+            && !self.is_absolute
+            && self.parts.len() > 1
+            && matches!(self.parts.first(), Some(Part::Tag(tag)) if tag.text == "~status")
+    }
+
+    // A free-standing attribute like `&effort=3h` or `&#12` that does not require a def
+    pub fn is_attribute(&self) -> bool {
+        !self.is_definition
+            && !self.is_absolute
+            && matches!(self.parts.as_slice(), [Part::KeyValue(_)] | [Part::Id(_)])
+    }
+
+    pub fn get_id(&self) -> Option<Id> {
+        self.parts.iter().find_map(|part| match part {
+            Part::Id(id) => Some(*id),
+            _ => None,
+        })
+    }
+
+    pub fn get_status(&self) -> Option<&Status> {
+        self.parts.iter().find_map(|part| match part {
+            Part::Status(status) => Some(status),
+            _ => None,
+        })
+    }
+
+    // Single-point Durations result in an Estimate without spread
+    pub fn get_estimate(&self) -> Option<Estimate> {
+        self.parts.iter().find_map(|part| match part {
+            Part::Duration(duration) => Some(Estimate::point(duration)),
+            Part::Estimate(estimate) => Some(estimate.clone()),
+            _ => None,
+        })
+    }
+
+    pub fn get_date(&self) -> Option<&Date> {
+        self.parts.iter().find_map(|part| match part {
+            Part::Date(date) => Some(date),
+            _ => None,
+        })
+    }
+
+    // Value of the KeyValue Part with given `key`, if present
+    pub fn get_value(&self, key: &str) -> Option<&str> {
+        self.parts.iter().find_map(|part| match part {
+            Part::KeyValue(KeyValue(k, value)) if is_same_text(k, key) => value.as_deref(),
+            _ => None,
+        })
+    }
+
+    // Checks if this Path contains a Tag with given `text`
+    pub fn has_tag(&self, text: &str) -> bool {
+        self.parts
+            .iter()
+            .any(|part| matches!(part, Part::Tag(tag) if is_same_text(&tag.text, text)))
+    }
+
+    pub fn get_scope(&self) -> Option<&Scope> {
+        self.parts.iter().find_map(|part| match part {
+            Part::Scope(scope) => Some(scope),
+            _ => None,
+        })
+    }
+    pub fn get_risk(&self) -> Option<&Risk> {
+        self.parts.iter().find_map(|part| match part {
+            Part::Risk(risk) => Some(risk),
+            _ => None,
+        })
+    }
+    pub fn get_effort(&self) -> Option<&Effort> {
+        self.parts.iter().find_map(|part| match part {
+            Part::Effort(effort) => Some(effort),
+            _ => None,
+        })
+    }
+
     pub fn get_prio(&self) -> Option<&Prio> {
         for part in &self.parts {
             match part {
@@ -230,7 +1003,24 @@ impl Path {
     }
 
     // `as_template` indicates if `self` is a template. If so, for non-Text Parts, only type compatibility is checked
-    pub fn matches_with(&self, rhs: &Self, as_template: bool) -> bool {
+    pub fn matches_with(&self, rhs: &Self, as_template: bool, env: &Env) -> bool {
+        self.matches_with_(rhs, as_template, false, env)
+    }
+
+    pub fn fuzzy_matches_with(&self, rhs: &Self, env: &Env) -> bool {
+        self.matches_with_(rhs, false, true, env)
+    }
+
+    // `fuzzy` indicates if Tags from `rhs` can match with Tags that are a few edits away, see is_similar_text()
+    fn matches_with_(&self, rhs: &Self, as_template: bool, fuzzy: bool, env: &Env) -> bool {
+        let same_text = |lhs: &str, rhs: &str| {
+            if fuzzy {
+                is_similar_text(lhs, rhs)
+            } else {
+                is_same_text(lhs, rhs)
+            }
+        };
+
         // For an absolute Path, we expect a match immediately, hence we act as if we already found a match
         let mut found_match = rhs.is_absolute;
 
@@ -238,35 +1028,53 @@ impl Path {
         let mut rhs = rhs.parts.iter();
 
         while let Some(rhs) = rhs.next() {
+            if let Part::Gap = rhs {
+                // Intermediate Parts from lhs can be skipped until the next match
+                found_match = false;
+                continue;
+            }
             while let Some(lhs) = lhs.next() {
                 let is_match = match (lhs, rhs) {
-                    (Part::Tag(lhs), Part::Tag(rhs)) => &lhs.text == &rhs.text,
+                    (Part::Tag(lhs), Part::Tag(rhs)) => same_text(&lhs.text, &rhs.text),
+                    (Part::KeyValue(lhs), Part::KeyValue(rhs)) => lhs.matches(rhs),
+                    (Part::KeyValue(lhs), Part::Tag(rhs)) => same_text(&lhs.0, &rhs.text),
+                    (Part::Id(lhs), Part::Id(rhs)) => lhs == rhs,
                     (Part::Status(lhs), Part::Status(rhs)) => as_template || lhs == rhs,
                     (Part::Date(lhs), Part::Date(rhs)) => as_template || lhs == rhs,
                     (Part::Duration(lhs), Part::Duration(rhs)) => as_template || lhs == rhs,
+                    (Part::Estimate(lhs), Part::Estimate(rhs)) => as_template || lhs == rhs,
+                    (Part::Duration(_), Part::Estimate(_)) => as_template,
                     (Part::Prio(lhs), Part::Prio(rhs)) => as_template || lhs == rhs,
+                    (Part::Scope(lhs), Part::Scope(rhs)) => as_template || lhs == rhs,
+                    (Part::Risk(lhs), Part::Risk(rhs)) => as_template || lhs == rhs,
+                    (Part::Effort(lhs), Part::Effort(rhs)) => as_template || lhs == rhs,
 
+                    (Part::Status(lhs), Part::Tag(rhs)) if fuzzy => same_text(&lhs.name, &rhs.text),
                     (Part::Status(lhs), Part::Tag(rhs)) => {
-                        if let Ok(rhs) = &Status::try_from(rhs.text.as_str()) {
+                        if let Ok(rhs) = &env.statuses.parse(&rhs.text) {
                             as_template || lhs == rhs
                         } else {
                             false
                         }
                     }
                     (Part::Date(lhs), Part::Tag(rhs)) => {
-                        if let Ok(rhs) = &Date::try_from(rhs.text.as_str()) {
+                        if let Ok(rhs) = &Date::parse(&rhs.text, &env.today) {
                             as_template || lhs == rhs
                         } else {
                             false
                         }
                     }
                     (Part::Duration(lhs), Part::Tag(rhs)) => {
-                        if let Ok(rhs) = &Duration::try_from(rhs.text.as_str()) {
+                        if let Ok(rhs) = &Duration::parse(&rhs.text, &env.calendar) {
                             as_template || lhs == rhs
                         } else {
-                            false
+                            as_template && Estimate::parse(&rhs.text, &env.calendar).is_ok()
                         }
                     }
+                    (Part::Estimate(lhs), Part::Tag(rhs)) => {
+                        Estimate::parse(&rhs.text, &env.calendar)
+                            .is_ok_and(|rhs| as_template || lhs == &rhs)
+                    }
                     (Part::Prio(lhs), Part::Tag(rhs)) => {
                         if let Ok(rhs) = &Prio::try_from(rhs.text.as_str()) {
                             as_template || lhs == rhs
@@ -274,6 +1082,12 @@ impl Path {
                             false
                         }
                     }
+                    (Part::Scope(lhs), Part::Tag(rhs)) => Scope::try_from(rhs.text.as_str())
+                        .is_ok_and(|rhs| as_template || lhs == &rhs),
+                    (Part::Risk(lhs), Part::Tag(rhs)) => Risk::try_from(rhs.text.as_str())
+                        .is_ok_and(|rhs| as_template || lhs == &rhs),
+                    (Part::Effort(lhs), Part::Tag(rhs)) => Effort::try_from(rhs.text.as_str())
+                        .is_ok_and(|rhs| as_template || lhs == &rhs),
 
                     _ => false,
                 };
@@ -299,8 +1113,11 @@ impl Path {
         true
     }
 
-    fn create_from_template(&self, rhs: &Self) -> Option<Path> {
+    // Also returns the amount of leading Parts from `self` that were skipped
+    fn create_from_template(&self, rhs: &Self, env: &Env) -> Option<(Path, usize)> {
         let mut ret = Path::new(rhs.is_definition, true, &[]);
+        let mut skipped = 0;
+        ret.is_dependency = rhs.is_dependency;
 
         // For an absolute Path, we expect a match immediately, hence we act as if we already found a match
         let mut found_match_before = rhs.is_absolute;
@@ -308,48 +1125,83 @@ impl Path {
         let mut lhs = self.parts.iter();
         let mut rhs = rhs.parts.iter();
         let mut cur_rhs_opt = rhs.next();
+        // Set when rhs contains a Gap: intermediate Parts from lhs are taken over
+        let mut in_gap = false;
 
         while let Some(lhs) = lhs.next() {
+            while let Some(Part::Gap) = cur_rhs_opt {
+                in_gap = true;
+                cur_rhs_opt = rhs.next();
+            }
             if let Some(cur_rhs) = cur_rhs_opt {
                 let part = match (lhs, cur_rhs) {
                     (Part::Tag(lhs), Part::Tag(rhs)) => {
-                        (&lhs.text == &rhs.text).then_some(Part::Tag(lhs.to_owned()))
+                        // Use the spelling from the template
+                        lhs.matches(rhs).then_some(Part::Tag(lhs.to_owned()))
                     }
+                    (Part::Tag(lhs), Part::KeyValue(rhs)) => is_same_text(&lhs.text, &rhs.0)
+                        .then(|| Part::KeyValue(KeyValue(lhs.text.clone(), rhs.1.clone()))),
+                    (Part::KeyValue(lhs), Part::KeyValue(rhs)) => lhs.matches(rhs).then(|| {
+                        Part::KeyValue(KeyValue(lhs.0.clone(), rhs.1.clone().or(lhs.1.clone())))
+                    }),
+                    (Part::KeyValue(lhs), Part::Tag(rhs)) => {
+                        is_same_text(&lhs.0, &rhs.text).then(|| Part::KeyValue(lhs.to_owned()))
+                    }
+                    (Part::Id(lhs), Part::Id(rhs)) => (lhs == rhs).then_some(Part::Id(*rhs)),
                     (Part::Status(_), Part::Status(rhs)) => Some(Part::Status(rhs.to_owned())),
                     (Part::Date(_), Part::Date(rhs)) => Some(Part::Date(rhs.to_owned())),
                     (Part::Duration(_), Part::Duration(rhs)) => {
                         Some(Part::Duration(rhs.to_owned()))
                     }
+                    (Part::Duration(_), Part::Estimate(rhs)) => {
+                        Some(Part::Estimate(rhs.to_owned()))
+                    }
                     (Part::Prio(_), Part::Prio(rhs)) => Some(Part::Prio(rhs.to_owned())),
+                    (Part::Scope(_), Part::Scope(rhs)) => Some(Part::Scope(*rhs)),
+                    (Part::Risk(_), Part::Risk(rhs)) => Some(Part::Risk(*rhs)),
+                    (Part::Effort(_), Part::Effort(rhs)) => Some(Part::Effort(*rhs)),
 
                     (Part::Status(_), Part::Tag(rhs)) => {
-                        if let Ok(rhs) = Status::try_from(rhs.text.as_str()) {
+                        if let Ok(rhs) = env.statuses.parse(&rhs.text) {
                             Some(Part::Status(rhs))
                         } else {
                             None
                         }
                     }
-                    (Part::Date(_), Part::Tag(rhs)) => {
-                        if let Ok(rhs) = Date::try_from(rhs.text.as_str()) {
+                    // &spec: a Tag is only interpreted as Date, Duration or Prio after the key of the template, eg `&due:friday` but not `&friday`
+                    (Part::Date(_), Part::Tag(rhs)) if found_match_before => {
+                        if let Ok(rhs) = Date::parse(&rhs.text, &env.today) {
                             Some(Part::Date(rhs))
                         } else {
                             None
                         }
                     }
-                    (Part::Duration(_), Part::Tag(rhs)) => {
-                        if let Ok(rhs) = Duration::try_from(rhs.text.as_str()) {
+                    // &spec: a `~duration` template also accepts a ranged Estimate like `2h..1d`
+                    (Part::Duration(_), Part::Tag(rhs)) if found_match_before => {
+                        if let Ok(rhs) = Duration::parse(&rhs.text, &env.calendar) {
                             Some(Part::Duration(rhs))
+                        } else if let Ok(rhs) = Estimate::parse(&rhs.text, &env.calendar) {
+                            Some(Part::Estimate(rhs))
                         } else {
                             None
                         }
                     }
-                    (Part::Prio(_), Part::Tag(rhs)) => {
+                    (Part::Prio(_), Part::Tag(rhs)) if found_match_before => {
                         if let Ok(rhs) = Prio::try_from(rhs.text.as_str()) {
                             Some(Part::Prio(rhs))
                         } else {
                             None
                         }
                     }
+                    (Part::Scope(_), Part::Tag(rhs)) => {
+                        Scope::try_from(rhs.text.as_str()).ok().map(Part::Scope)
+                    }
+                    (Part::Risk(_), Part::Tag(rhs)) => {
+                        Risk::try_from(rhs.text.as_str()).ok().map(Part::Risk)
+                    }
+                    (Part::Effort(_), Part::Tag(rhs)) => {
+                        Effort::try_from(rhs.text.as_str()).ok().map(Part::Effort)
+                    }
 
                     _ => None,
                 };
@@ -357,14 +1209,18 @@ impl Path {
                 if let Some(part) = part {
                     // Once we found a match, we expect we keep matching
                     found_match_before = true;
+                    in_gap = false;
                     ret.parts.push(part);
                     cur_rhs_opt = rhs.next();
+                } else if in_gap {
+                    ret.parts.push(lhs.clone());
                 } else {
                     if found_match_before {
                         // Found a mismatch
                         return None;
                     } else {
                         ret.parts.push(lhs.clone());
+                        skipped += 1;
                     }
                 }
             } else {
@@ -373,60 +1229,207 @@ impl Path {
             }
         }
 
+        while let Some(Part::Gap) = cur_rhs_opt {
+            cur_rhs_opt = rhs.next();
+        }
         if cur_rhs_opt.is_some() {
             // Could not match all parts from rhs
             return None;
         }
 
         // Could match all parts from rhs: we found a match
-        Some(ret)
+        Some((ret, skipped))
+    }
+}
+
+impl Part {
+    // Name of the template for typed Parts, eg `~date`
+    pub fn template_name(&self) -> Option<&'static str> {
+        match self {
+            Part::Status(_) => Some("~status"),
+            Part::Date(_) => Some("~date"),
+            Part::Duration(_) | Part::Estimate(_) => Some("~duration"),
+            Part::Prio(_) => Some("~priority"),
+            Part::Scope(_) => Some("~scope"),
+            Part::Risk(_) => Some("~risk"),
+            Part::Effort(_) => Some("~effort"),
+            _ => None,
+        }
     }
 }
 
 impl std::fmt::Display for Path {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write_with(f, &Calendar::default())
+    }
+}
+
+impl Path {
+    // Formats a def with its typed Parts as template, eg `!:due:~date` iso `!:due:0000-00-00`
+    pub fn to_template_string(&self) -> String {
+        let mut path = self.clone();
+        for part in &mut path.parts {
+            if let Some(name) = part.template_name() {
+                *part = Part::Tag(Tag::from(name));
+            }
+        }
+        path.to_string()
+    }
+
+    // Durations are formatted according to `calendar`
+    pub fn to_string_with(&self, calendar: &Calendar) -> String {
+        let mut s = String::new();
+        let _ = self.write_with(&mut s, calendar);
+        s
+    }
+    fn write_with(&self, w: &mut impl std::fmt::Write, calendar: &Calendar) -> std::fmt::Result {
         if self.is_definition {
-            write!(f, "!")?;
+            write!(w, "!")?;
         }
         if self.is_absolute {
-            write!(f, ":")?;
+            write!(w, ":")?;
         }
 
         let mut first = true;
         for part in &self.parts {
             if !first {
-                write!(f, ":")?;
+                write!(w, ":")?;
             }
             first = false;
-            write!(f, "{part}")?;
+            match part {
+                Part::Duration(v) => write!(w, "{}", v.to_string_with(calendar))?,
+                Part::Estimate(v) => write!(w, "{}", v.to_string_with(calendar))?,
+                _ => write!(w, "{part}")?,
+            }
+        }
+
+        if self.is_dependency {
+            write!(w, "&")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Tag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", &self.text)?;
+        if self.exclusive {
+            write!(f, "!")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Part {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Part::Tag(v) => write!(f, "{v}"),
+            Part::Status(v) => write!(f, "{v}"),
+            Part::Date(v) => write!(f, "{v}"),
+            Part::Duration(v) => write!(f, "{v}"),
+            Part::Estimate(v) => write!(f, "{v}"),
+            Part::Prio(v) => write!(f, "{v}"),
+            Part::Scope(v) => write!(f, "{v}"),
+            Part::Risk(v) => write!(f, "{v}"),
+            Part::Effort(v) => write!(f, "{v}"),
+            Part::KeyValue(v) => write!(f, "{v}"),
+            Part::Id(v) => write!(f, "{v}"),
+            // Surrounded by the `:` separators, this results in `::`
+            Part::Gap => Ok(()),
+        }
+    }
+}
+
+impl KeyValue {
+    // Keys must match. When both have a value, these must match as well.
+    pub fn matches(&self, rhs: &KeyValue) -> bool {
+        is_same_text(&self.0, &rhs.0)
+            && match (&self.1, &rhs.1) {
+                (Some(lhs), Some(rhs)) => is_same_text(lhs, rhs),
+                _ => true,
+            }
+    }
+}
+
+impl std::fmt::Display for KeyValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}=", &self.0)?;
+        if let Some(value) = &self.1 {
+            write!(f, "{value}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Id {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+// &spec: an Id is `#` followed by digits only, eg `#12`
+impl TryFrom<&str> for Id {
+    type Error = util::ErrorType;
+    fn try_from(s: &str) -> std::result::Result<Id, Self::Error> {
+        match s.strip_prefix('#') {
+            Some(digits) if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) => {
+                Ok(Id(digits.parse()?))
+            }
+            _ => Err(util::Error::create(format!(
+                "Id '{s}' should be '#' followed by digits"
+            ))),
+        }
+    }
+}
+
+// &spec: a Part like `#12` is an Id, a Part containing `=` is a KeyValue, otherwise it is a Tag
+impl From<&str> for Part {
+    fn from(s: &str) -> Self {
+        if let Ok(id) = Id::try_from(s) {
+            return Part::Id(id);
+        }
+        match s.split_once('=') {
+            Some((key, value)) => Part::KeyValue(KeyValue(
+                key.into(),
+                (!value.is_empty()).then(|| value.into()),
+            )),
+            None => Part::Tag(Tag::from(s)),
         }
+    }
+}
 
-        Ok(())
+impl Tag {
+    // Tags match case-insensitively and independent of their Unicode normalization form
+    pub fn matches(&self, rhs: &Tag) -> bool {
+        is_same_text(&self.text, &rhs.text)
     }
 }
 
-impl std::fmt::Display for Tag {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", &self.text)?;
-        if self.exclusive {
-            write!(f, "!")?;
-        }
-        Ok(())
+// Same text, or at most 1 edit away for texts up to 5 chars and 2 edits for longer texts
+pub fn is_similar_text(lhs: &str, rhs: &str) -> bool {
+    if is_same_text(lhs, rhs) {
+        return true;
     }
+    let max_edits = if rhs.chars().count() <= 5 { 1 } else { 2 };
+    fuzz::edit_distance(&lhs.to_lowercase(), &rhs.to_lowercase()) <= max_edits
 }
 
-impl std::fmt::Display for Part {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Part::Tag(v) => write!(f, "{v}"),
-            Part::Status(v) => write!(f, "{v}"),
-            Part::Date(v) => write!(f, "{v}"),
-            Part::Duration(v) => write!(f, "{v}"),
-            Part::Prio(v) => write!(f, "{v}"),
-        }
+// Compares `lhs` and `rhs` case-insensitively, after NFKC normalization
+pub fn is_same_text(lhs: &str, rhs: &str) -> bool {
+    if lhs == rhs {
+        true
+    } else if lhs.is_ascii() && rhs.is_ascii() {
+        lhs.eq_ignore_ascii_case(rhs)
+    } else {
+        normalize(lhs) == normalize(rhs)
     }
 }
 
+pub fn normalize(s: &str) -> String {
+    s.nfkc().flat_map(char::to_lowercase).collect()
+}
+
 impl From<&str> for Tag {
     fn from(value: &str) -> Self {
         let mut text = value;
@@ -444,101 +1447,73 @@ impl From<&str> for Tag {
     }
 }
 
-impl TryFrom<&str> for Status {
+impl TryFrom<&Path> for StatusDef {
     type Error = util::ErrorType;
 
-    fn try_from(s: &str) -> std::result::Result<Status, Self::Error> {
-        match s {
-            "todo" => Ok(Status::Todo),
-            "wip" => Ok(Status::Wip),
-            "done" => Ok(Status::Done),
-            _ => Err(util::Error::create("This is not a valid status")),
+    fn try_from(path: &Path) -> std::result::Result<StatusDef, Self::Error> {
+        let mut texts = path.parts.iter().map(|part| match part {
+            Part::Tag(tag) => Ok(tag.text.as_str()),
+            _ => Err(util::Error::create(format!(
+                "Status definition '{path}' can only contain text"
+            ))),
+        });
+
+        if texts.next().transpose()? != Some("~status") {
+            return Err(util::Error::create(format!(
+                "Status definition '{path}' should start with '~status'"
+            )));
         }
+        let Some(name) = texts.next().transpose()? else {
+            return Err(util::Error::create(format!(
+                "Status definition '{path}' should contain a name"
+            )));
+        };
+        let is_open = match texts.next().transpose()? {
+            None | Some("open") => true,
+            Some("closed") => false,
+            Some(s) => {
+                return Err(util::Error::create(format!(
+                    "Expected 'open' or 'closed' iso '{s}' in Status definition '{path}'"
+                )))
+            }
+        };
+        let transitions = texts.collect::<util::Result<Vec<&str>>>()?;
+
+        Ok(StatusDef::new(name, is_open, &transitions))
     }
 }
 
+// Only absolute Dates, use Date::parse() for relative Dates
 impl TryFrom<&str> for Date {
     type Error = util::ErrorType;
 
     fn try_from(s: &str) -> std::result::Result<Date, Self::Error> {
-        let year;
-        let month;
-        let day;
-
-        {
-            let mut strange = strange::Strange::new(s);
-
-            if let Some(s) = strange.read_decimals(4) {
-                year = strange::Strange::new(s).read_number::<u16>().unwrap();
-            } else {
-                return Err(util::Error::create("Could not read year for Date"));
-            }
-
-            strange.read_char_if('-');
-
-            if let Some(s) = strange.read_decimals(2) {
-                month = strange::Strange::new(s).read_number::<u8>().unwrap();
-            } else {
-                return Err(util::Error::create("Could not read month for Date"));
-            }
-
-            strange.read_char_if('-');
-
-            if let Some(s) = strange.read_decimals(2) {
-                day = strange::Strange::new(s).read_number::<u8>().unwrap();
-            } else {
-                return Err(util::Error::create("Could not read day for Date"));
-            }
-        }
-
-        Ok(Date { year, month, day })
+        Date::parse_absolute(s)
     }
 }
 
 impl TryFrom<&str> for Duration {
     type Error = util::ErrorType;
+    // Uses the default Calendar, use Duration::parse() for the configured Calendar
     fn try_from(s: &str) -> std::result::Result<Duration, Self::Error> {
-        let mut minutes = 0 as u32;
-
-        {
-            let mut strange = strange::Strange::new(s);
-            while !strange.is_empty() {
-                if let Some(v) = strange.read_number::<u32>() {
-                    if !strange.read_char_when(|ch| {
-                        match ch {
-                            'w' => minutes += v * 60 * 8 * 5,
-                            'd' => minutes += v * 60 * 8,
-                            'h' => minutes += v * 60,
-                            'm' => minutes += v,
-                            _ => return false,
-                        }
-                        true
-                    }) {
-                        return Err(util::Error::create(format!(
-                            "Unexpected unit found in Duration '{}'",
-                            s
-                        )));
-                    }
-                } else {
-                    return Err(util::Error::create(format!(
-                        "Could not read number from Duration '{}'",
-                        s
-                    )));
-                }
-            }
-        }
-
-        Ok(Duration { minutes })
+        Duration::parse(s, &Calendar::default())
     }
 }
 
 impl std::fmt::Display for Status {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Status::Todo => write!(f, "Todo"),
-            Status::Wip => write!(f, "Wip"),
-            Status::Done => write!(f, "Done"),
+        write!(f, "{}", &self.name)
+    }
+}
+
+impl std::fmt::Display for StatusDef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let openness = if self.is_open { "open" } else { "closed" };
+        write!(f, "~status:{}:{openness}", &self.name)?;
+        for transition in &self.transitions {
+            write!(f, ":{transition}")?;
         }
+        Ok(())
     }
 }
 
@@ -551,24 +1526,21 @@ impl std::fmt::Display for Date {
 
 impl std::fmt::Display for Duration {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut m = self.minutes;
-        let mut did_write = false;
-        let mut cb = |div: u32, suffix: char| {
-            let n = m / div;
-            if n > 0 {
-                write!(f, "{n}{suffix}").unwrap();
-                did_write = true;
-                m -= n * div;
-            }
-        };
-        cb(60 * 8 * 5, 'w');
-        cb(60 * 8, 'd');
-        cb(60, 'h');
-        cb(1, 'm');
-        if !did_write {
-            write!(f, "0m").unwrap();
-        }
-        Ok(())
+        write!(f, "{}", self.to_string_with(&Calendar::default()))
+    }
+}
+
+impl TryFrom<&str> for Estimate {
+    type Error = util::ErrorType;
+    // Uses the default Calendar, use Estimate::parse() for the configured Calendar
+    fn try_from(s: &str) -> std::result::Result<Estimate, Self::Error> {
+        Estimate::parse(s, &Calendar::default())
+    }
+}
+
+impl std::fmt::Display for Estimate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_string_with(&Calendar::default()))
     }
 }
 
@@ -630,11 +1602,27 @@ impl TryFrom<&str> for Path {
 
         let is_definition = strange.read_char_if('!');
         let is_absolute = strange.read_char_if(':');
+        let is_dependency = strange.unwrite_char_if('&');
+
+        // &spec: consecutive colons like `::` result in a Gap, except at the end
+        let read_gap = |strange: &mut strange::Strange| {
+            let mut is_gap = false;
+            while strange.read_char_if(':') {
+                is_gap = true;
+            }
+            is_gap && !strange.is_empty()
+        };
 
         let mut parts = Vec::<Part>::new();
+        if is_absolute && read_gap(&mut strange) {
+            parts.push(Part::Gap);
+        }
         while !strange.is_empty() {
             if let Some(str) = strange.read(|b| b.exclude().to_end().through(':')) {
-                parts.push(Part::Tag(Tag::from(str)));
+                parts.push(Part::from(str));
+                if read_gap(&mut strange) {
+                    parts.push(Part::Gap);
+                }
             }
         }
 
@@ -642,13 +1630,11 @@ impl TryFrom<&str> for Path {
             is_definition,
             is_absolute,
             parts,
+            is_dependency,
         })
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Clone)]
-pub struct KeyValue(pub String, pub Option<String>);
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -667,7 +1653,7 @@ mod tests {
             }
 
             let needle = Path::try_from(needle)?;
-            assert_eq!(paths.matches_with(&needle), exp);
+            assert_eq!(paths.matches_with(&needle, &Env::default()), exp);
         }
         Ok(())
     }
@@ -687,11 +1673,26 @@ mod tests {
             ("ab", "a", false),
             ("ab", "b", false),
             (":a:b", "c", false),
+            // Case-insensitive and normalized
+            (":Abc:b", "abc:B", true),
+            (":caf\u{e9}", "CAFE\u{301}", true),
+            (":\u{c5}ngstr\u{f6}m", "\u{e5}NGSTR\u{f6}M", true),
+            // Gap
+            (":a:b", "a::b", true),
+            (":a:b:c", "a::c", true),
+            (":a:b:c:d", "a::d", true),
+            (":a:b:c:d", "a::c:d", true),
+            (":a:b:c:d", "a::b::d", true),
+            (":a:b:c", "::c", true),
+            (":a:b:c", ":a::c", true),
+            (":a:b:c", ":b::c", false),
+            (":a:b:c", "c::a", false),
+            (":a:b:c:d", "a::c:a", false),
         ];
         for (path, needle, exp) in scns {
             let path = Path::try_from(path)?;
             let needle = Path::try_from(needle)?;
-            assert_eq!(path.matches_with(&needle, true), exp);
+            assert_eq!(path.matches_with(&needle, true, &Env::default()), exp);
         }
         Ok(())
     }
@@ -712,7 +1713,10 @@ mod tests {
         for (needle, as_template, exp) in scns {
             println!("----------------- {needle} {exp}");
             let needle = Path::try_from(needle)?;
-            assert_eq!(path.matches_with(&needle, as_template), exp);
+            assert_eq!(
+                path.matches_with(&needle, as_template, &Env::default()),
+                exp
+            );
         }
         Ok(())
     }
@@ -726,17 +1730,22 @@ mod tests {
 
         let scns = [
             (&abc, "abc", Some("abc"), None),
+            (&abc, "ABC", Some("abc"), None),
             (&abc, "abd", None, None),
             (&prio, "prio", None, None),
             (&prio, "prio:aa0", None, None),
             (&prio, "prio:A0", Some("prio"), Some("A0")),
             (&prio, "prio:a0", Some("prio"), Some("a0")),
             (&prio, "prio:b1", Some("prio"), Some("b1")),
+            // Without the key, the Tag is not interpreted as Prio
+            (&prio, "b1", None, None),
         ];
         for (template, path, base, prio) in scns {
             println!("----------------- {path} {:?} {:?}", base, prio);
             let path = Path::try_from(path)?;
-            let new_path = template.create_from_template(&path);
+            let new_path = template
+                .create_from_template(&path, &Env::default())
+                .map(|(path, _)| path);
             let exp = base.map(|base| {
                 let mut p = Path::new(false, true, &[base]);
                 if let Some(prio) = prio {
@@ -757,6 +1766,27 @@ mod tests {
             ("a:b", Some(Path::new(false, false, &["a", "b"]))),
             (":", Some(Path::new(false, true, &[]))),
             (":a:b", Some(Path::new(false, true, &["a", "b"]))),
+            (
+                "a:b&",
+                Some(Path {
+                    is_dependency: true,
+                    ..Path::new(false, false, &["a", "b"])
+                }),
+            ),
+            (
+                "a::b",
+                Some(Path {
+                    parts: vec![Part::from("a"), Part::Gap, Part::from("b")],
+                    ..Path::new(false, false, &[])
+                }),
+            ),
+            (
+                "::a",
+                Some(Path {
+                    parts: vec![Part::Gap, Part::from("a")],
+                    ..Path::new(false, true, &[])
+                }),
+            ),
         ];
 
         for (s, exp) in scns {
@@ -765,6 +1795,55 @@ mod tests {
                 assert_eq!(exp.to_string(), s);
             }
         }
+
+        // Repeated and trailing colons
+        let scns = [("a:::b", "a::b"), ("a::", "a"), (":::a", "::a")];
+        for (s, exp) in scns {
+            assert_eq!(
+                Path::try_from(s).map(|p| p.to_string()).ok(),
+                Some(exp.into())
+            );
+        }
+    }
+
+    #[test]
+    fn test_key_value() -> util::Result<()> {
+        let scns = [
+            (
+                "owner=alice",
+                KeyValue("owner".into(), Some("alice".into())),
+            ),
+            ("owner=", KeyValue("owner".into(), None)),
+            ("a=b=c", KeyValue("a".into(), Some("b=c".into()))),
+        ];
+        for (s, exp) in scns {
+            assert_eq!(Part::from(s), Part::KeyValue(exp.clone()));
+            assert_eq!(exp.to_string(), s);
+        }
+
+        let path = Path::try_from(":team:owner=Alice")?;
+        let scns = [
+            ("owner=alice", true),
+            ("owner", true),
+            ("owner=", true),
+            ("owner=bob", false),
+            ("team:owner=alice", true),
+        ];
+        for (needle, exp) in scns {
+            assert_eq!(
+                path.matches_with(&Path::try_from(needle)?, false, &Env::default()),
+                exp
+            );
+        }
+
+        let mut defs = Paths::new();
+        defs.insert(Path::try_from("!:team:Owner")?);
+        assert_eq!(
+            defs.resolve(&Path::try_from("owner=alice")?, &Env::default()),
+            Resolution::Unique(Path::try_from(":team:Owner=alice")?)
+        );
+
+        Ok(())
     }
 
     #[test]
@@ -793,25 +1872,171 @@ mod tests {
     }
 
     #[test]
-    fn test_status_try_from() {
-        let scns = [("todo", Some(Status::Todo))];
+    fn test_id_try_from() {
+        let scns = [
+            ("#12", Some(Id(12))),
+            ("#0", Some(Id(0))),
+            ("#", None),
+            ("#1a", None),
+            ("#-1", None),
+            ("12", None),
+        ];
+
+        for (s, exp) in scns {
+            assert_eq!(Id::try_from(s).ok(), exp, "{s}");
+            if let Some(id) = exp {
+                assert_eq!(Part::from(s), Part::Id(id));
+                assert_eq!(id.to_string(), s);
+            }
+        }
+        assert_eq!(Part::from("#1a"), Part::Tag(Tag::from("#1a")));
+    }
+
+    #[test]
+    fn test_statuses_parse() {
+        let scns = [
+            (
+                "todo",
+                Some(Status {
+                    name: "todo".into(),
+                    is_open: true,
+                }),
+            ),
+            (
+                "done",
+                Some(Status {
+                    name: "done".into(),
+                    is_open: false,
+                }),
+            ),
+            ("unknown", None),
+        ];
+
+        let statuses = Statuses::default();
+        for (s, exp) in scns {
+            assert_eq!(statuses.parse(s).ok(), exp);
+            if let Some(exp) = exp {
+                assert_eq!(exp.to_string(), s);
+            }
+        }
+    }
+
+    #[test]
+    fn test_status_def_try_from() -> util::Result<()> {
+        let scns = [
+            ("!~status:review", Some(StatusDef::new("review", true, &[]))),
+            (
+                "!~status:cancelled:closed",
+                Some(StatusDef::new("cancelled", false, &[])),
+            ),
+            (
+                "!~status:review:open:wip:done",
+                Some(StatusDef::new("review", true, &["wip", "done"])),
+            ),
+            ("!~status:review:maybe", None),
+            ("!~status", None),
+        ];
 
         for (s, exp) in scns {
-            assert_eq!(Status::try_from(s).ok(), exp);
-            assert_eq!(exp.unwrap().to_string(), s);
+            let path = Path::try_from(s)?;
+            assert_eq!(StatusDef::try_from(&path).ok(), exp);
+            if let Some(exp) = exp {
+                assert!(path.is_status_def());
+                assert_eq!(format!("!{exp}").starts_with(s), true);
+            }
         }
+        Ok(())
+    }
+
+    #[test]
+    fn test_statuses_insert() {
+        let mut statuses = Statuses::default();
+        let count = statuses.data.len();
+        statuses.insert(StatusDef::new("review", true, &["wip", "done", "shipped"]));
+        statuses.insert(StatusDef::new("done", true, &[]));
+
+        assert_eq!(statuses.data.len(), count + 1);
+        assert_eq!(statuses.get("review").map(|def| def.is_open), Some(true));
+        assert_eq!(statuses.get("done").map(|def| def.is_open), Some(true));
+        assert_eq!(statuses.get("unknown"), None);
+
+        assert_eq!(statuses.unknown_transitions(), [("review", "shipped")]);
     }
 
     #[test]
     fn test_date_try_from() {
-        let scns = [("2024-10-02", Some(Date::new(2024, 10, 2)))];
+        let scns = [
+            ("2024-10-02", Some(Date::new(2024, 10, 2))),
+            ("2024-02-29", Some(Date::new(2024, 2, 29))),
+            ("2023-02-29", None),
+            ("2024-13-45", None),
+            ("2024-04-31", None),
+            ("2024-00-10", None),
+            ("2024-10-02x", None),
+        ];
 
         for (s, exp) in scns {
             assert_eq!(Date::try_from(s).ok(), exp);
-            assert_eq!(exp.unwrap().to_string(), s);
+            if let Some(exp) = exp {
+                assert_eq!(exp.to_string(), s);
+            }
+        }
+    }
+
+    #[test]
+    fn test_date_parse_relative() {
+        // A Tuesday
+        let today = Date::new(2024, 12, 31);
+        assert_eq!(today.weekday(), 1);
+
+        let scns = [
+            ("today", Some(Date::new(2024, 12, 31))),
+            ("Tomorrow", Some(Date::new(2025, 1, 1))),
+            ("yesterday", Some(Date::new(2024, 12, 30))),
+            ("tuesday", Some(Date::new(2024, 12, 31))),
+            ("Wednesday", Some(Date::new(2025, 1, 1))),
+            ("monday", Some(Date::new(2025, 1, 6))),
+            ("next-tuesday", Some(Date::new(2025, 1, 7))),
+            ("next-wednesday", Some(Date::new(2025, 1, 1))),
+            ("wed", None),
+            ("mon", None),
+            ("sun", None),
+            ("next-mon", None),
+            ("+3d", Some(Date::new(2025, 1, 3))),
+            ("-1w", Some(Date::new(2024, 12, 24))),
+            ("+1w2d", Some(Date::new(2025, 1, 9))),
+            ("+3x", None),
+            ("mo", None),
+        ];
+
+        for (s, exp) in scns {
+            assert_eq!(Date::parse(s, &today).ok(), exp);
         }
     }
 
+    #[test]
+    fn test_date_days() {
+        let scns = [
+            (Date::new(1970, 1, 1), 0),
+            (Date::new(1970, 1, 2), 1),
+            (Date::new(1969, 12, 31), -1),
+            (Date::new(2000, 3, 1), 11017),
+            (Date::new(2024, 10, 4), 20000),
+        ];
+
+        for (date, days) in scns {
+            assert_eq!(date.to_days(), days);
+            assert_eq!(Date::from_days(days), date);
+        }
+
+        let a = Date::new(2024, 2, 28);
+        let b = Date::new(2024, 3, 1);
+        assert_eq!(b.days_since(&a), 2);
+        assert_eq!(a.days_since(&b), -2);
+        assert!(a < b);
+        assert_eq!(a.add_days(2), b);
+    }
+
     #[test]
     fn test_duration_try_from() {
         let scns = [
@@ -828,6 +2053,92 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_duration_calendar() {
+        let calendar = Calendar {
+            hours_per_day: 6,
+            days_per_week: 4,
+            holidays: Vec::new(),
+        };
+
+        let scns = [
+            ("1w", 4 * 6 * 60, "1w"),
+            ("4d", 4 * 6 * 60, "1w"),
+            ("1d7h", 13 * 60, "2d1h"),
+            ("90m", 90, "1h30m"),
+        ];
+
+        for (s, minutes, exp) in scns {
+            let duration = Duration::parse(s, &calendar).unwrap();
+            assert_eq!(duration.minutes(), minutes);
+            assert_eq!(duration.to_string_with(&calendar), exp);
+        }
+        assert_eq!(
+            Duration::from_calendar(1, 1, 0, 0, &calendar).minutes(),
+            5 * 6 * 60
+        );
+    }
+
+    #[test]
+    fn test_estimate_parse() {
+        let calendar = Calendar {
+            hours_per_day: 8,
+            days_per_week: 5,
+            holidays: Vec::new(),
+        };
+
+        let scns = [
+            ("2h..1d", Some((300, 3600.0)), "2h..1d"),
+            ("1h..2h..5h", Some((140, 1600.0)), "1h..2h..5h"),
+            ("3h..3h", Some((180, 0.0)), "3h"),
+            ("1d..2h", None, ""),
+            ("1h..4h..2h", None, ""),
+            ("1h", None, ""),
+            ("1h..2h..3h..4h", None, ""),
+        ];
+
+        for (s, exp, exp_str) in scns {
+            let estimate = Estimate::parse(s, &calendar).ok();
+            assert_eq!(
+                estimate
+                    .as_ref()
+                    .map(|e| (e.expected().minutes(), e.variance())),
+                exp,
+                "{s}"
+            );
+            if let Some(estimate) = estimate {
+                assert_eq!(estimate.to_string_with(&calendar), exp_str);
+            }
+        }
+    }
+
+    #[test]
+    fn test_calendar_dates() {
+        // 2024-12-23 is a Monday, 2024-12-25 is a holiday
+        let calendar = Calendar {
+            hours_per_day: 8,
+            days_per_week: 4,
+            holidays: vec![Date::new(2024, 12, 25)],
+        };
+        let monday = Date::new(2024, 12, 23);
+
+        assert!(calendar.is_workday(&monday));
+        assert!(!calendar.is_workday(&Date::new(2024, 12, 25)));
+        assert!(!calendar.is_workday(&Date::new(2024, 12, 27)));
+
+        let scns = [
+            ("0m", Date::new(2024, 12, 23)),
+            ("1d", Date::new(2024, 12, 23)),
+            ("1d1h", Date::new(2024, 12, 24)),
+            ("3d", Date::new(2024, 12, 26)),
+            ("4d", Date::new(2024, 12, 30)),
+        ];
+        for (duration, exp) in scns {
+            let duration = Duration::parse(duration, &calendar).unwrap();
+            assert_eq!(calendar.add_duration(&monday, &duration), exp);
+        }
+    }
+
     #[test]
     fn test_prio_try_from() {
         let scns = [
@@ -844,6 +2155,31 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_scope_risk_effort_try_from() {
+        let scns = [
+            ("must", Some(Scope::Must), "must"),
+            ("Should", Some(Scope::Should), "should"),
+            ("wont", Some(Scope::Deferred), "deferred"),
+            ("maybe", None, ""),
+        ];
+        for (s, exp, exp_str) in scns {
+            let scope = Scope::try_from(s).ok();
+            assert_eq!(scope, exp);
+            if let Some(scope) = scope {
+                assert_eq!(scope.to_string(), exp_str);
+            }
+        }
+
+        assert_eq!(Risk::try_from("HIGH").ok(), Some(Risk::High));
+        assert_eq!(Risk::try_from("huge").ok(), None);
+        assert_eq!(Effort::try_from("xl").ok(), Some(Effort::XL));
+        assert_eq!(Effort::try_from("small").ok(), Some(Effort::S));
+        assert_eq!(Effort::try_from("med").ok(), Some(Effort::M));
+        assert_eq!(Effort::try_from("medium").ok(), None);
+        assert_eq!(Effort::S.to_string(), "s");
+    }
+
     #[test]
     fn test_paths_has_variant() -> util::Result<()> {
         let mut paths = Paths::new();
@@ -883,14 +2219,70 @@ mod tests {
         // paths.insert(Path::try_from("!:blocked")?);
 
         assert_eq!(
-            paths.resolve(&Path::try_from("todo!")?),
-            Path::try_from(":todo!").ok()
+            paths.resolve(&Path::try_from("todo!")?, &Env::default()),
+            Resolution::Unique(Path::try_from(":todo!")?)
         );
         assert_eq!(
-            paths.resolve(&Path::try_from("todo")?),
-            Path::try_from(":todo!").ok()
+            paths.resolve(&Path::try_from("todo")?, &Env::default()),
+            Resolution::Unique(Path::try_from(":todo!")?)
         );
 
         Ok(())
     }
+
+    #[test]
+    fn test_paths_resolve_ambiguous() -> util::Result<()> {
+        let mut paths = Paths::new();
+        paths.insert(Path::try_from("!:x")?);
+        paths.insert(Path::try_from("!:a:x")?);
+        paths.insert(Path::try_from("!:a:y")?);
+        paths.insert(Path::try_from("!:b:y")?);
+        paths.insert(Path::try_from("!:b:z")?);
+
+        let scns = [
+            // Full match takes precedence
+            ("x", Resolution::Unique(Path::try_from(":x")?)),
+            ("a:x", Resolution::Unique(Path::try_from(":a:x")?)),
+            // Unique partial match
+            ("z", Resolution::Unique(Path::try_from(":b:z")?)),
+            // Ambiguous partial match
+            ("y", Resolution::Ambiguous(vec![2, 3])),
+            ("b:y", Resolution::Unique(Path::try_from(":b:y")?)),
+            ("w", Resolution::Unresolved),
+        ];
+        for (rel, exp) in scns {
+            assert_eq!(paths.resolve(&Path::try_from(rel)?, &Env::default()), exp);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_paths_resolve_gap() -> util::Result<()> {
+        let mut paths = Paths::new();
+        paths.insert(Path::try_from("!:proj:area:task")?);
+        paths.insert(Path::try_from("!:other:task")?);
+
+        let scns = [
+            (
+                "proj::task",
+                Resolution::Unique(Path::try_from(":proj:area:task")?),
+            ),
+            (
+                "::area:task",
+                Resolution::Unique(Path::try_from(":proj:area:task")?),
+            ),
+            ("::task", Resolution::Ambiguous(vec![0, 1])),
+            (
+                "other::task",
+                Resolution::Unique(Path::try_from(":other:task")?),
+            ),
+            ("area::proj", Resolution::Unresolved),
+        ];
+        for (rel, exp) in scns {
+            assert_eq!(paths.resolve(&Path::try_from(rel)?, &Env::default()), exp);
+        }
+
+        Ok(())
+    }
 }
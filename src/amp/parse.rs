@@ -23,6 +23,8 @@ pub enum Kind {
 pub enum Match {
     Everywhere,
     OnlyStart,
+    // Like Everywhere, but a word starting with `!` is a definition as well, eg `!:status:~status` in `.amp` files
+    BareDefs,
 }
 
 type Range = std::ops::Range<usize>;
@@ -107,26 +109,46 @@ impl Parser {
                             return false;
                         };
 
-                        if !pop(&lex::Kind::Ampersand) {
+                        let has_ampersand = pop(&lex::Kind::Ampersand);
+                        let is_definition = pop(&lex::Kind::Bang);
+                        if !has_ampersand && (!is_definition || m != &Match::BareDefs) {
                             fail!("Expected group to start with `&`");
                         }
-                        let is_definition = pop(&lex::Kind::Bang);
+                        // &spec: a leading `::` anchors the Path at the root, followed by a Gap
+                        let is_gap = |ix: usize| {
+                            group.tokens.get(ix).is_some_and(|token| {
+                                token.kind == lex::Kind::Colon && token.range.len() > 1
+                            })
+                        };
                         let is_absolute = pop(&lex::Kind::Colon);
+                        let starts_with_gap = is_absolute && is_gap(range.start - 1);
+
+                        // &spec: a trailing `&` marks a dependency
+                        let is_dependency = !range.is_empty()
+                            && group.tokens.last().map(|token| &token.kind)
+                                == Some(&lex::Kind::Ampersand);
+                        if is_dependency {
+                            range.end -= 1;
+                        }
 
-                        let mut parts = Vec::<String>::new();
-                        let mut part = None;
-                        for ix in range {
+                        let mut parts = Vec::<amp::Part>::new();
+                        if starts_with_gap && !range.is_empty() {
+                            parts.push(amp::Part::Gap);
+                        }
+                        let mut part: Option<String> = None;
+                        for ix in range.clone() {
                             if let Some(token) = group.tokens.get(ix) {
                                 match token.kind {
-                                    lex::Kind::Colon => match token.range.len() {
-                                        // &todo: Support multi-colon
-                                        _ => {
-                                            if let Some(part) = part {
-                                                parts.push(part);
-                                            }
-                                            part = None;
+                                    lex::Kind::Colon => {
+                                        if let Some(part) = part {
+                                            parts.push(amp::Part::from(part.as_str()));
                                         }
-                                    },
+                                        part = None;
+                                        // &spec: `::` skips intermediate levels, a trailing `::` is ignored
+                                        if is_gap(ix) && ix + 1 < range.end {
+                                            parts.push(amp::Part::Gap);
+                                        }
+                                    }
                                     _ => {
                                         if let Some(s) = content.get(token.range.clone()) {
                                             if let Some(part) = &mut part {
@@ -142,17 +164,13 @@ impl Parser {
                             }
                         }
                         if let Some(part) = part {
-                            parts.push(part);
+                            parts.push(amp::Part::from(part.as_str()));
                         }
-
-                        let parts = parts
-                            .into_iter()
-                            .map(|part| amp::Part::Tag(amp::Tag::from(part.as_str())))
-                            .collect();
                         let path = amp::Path {
                             is_definition,
                             is_absolute,
                             parts,
+                            is_dependency,
                         };
                         stmt.kind = Kind::Amp(path);
                     }
@@ -210,24 +228,40 @@ impl<'a> Grouper<'a> {
         let mut is_first = true;
         let mut last_was_space = true;
         let mut m: Match = m.clone();
-        for token in tokens {
+        for (ix, token) in tokens.iter().enumerate() {
             match self.state {
                 State::Text => {
-                    if token.kind == lex::Kind::Ampersand
+                    let is_amp =
+                        token.kind == lex::Kind::Ampersand && (is_first || m != Match::OnlyStart);
+                    // &spec: a bare definition needs something after the `!`
+                    let is_bare_def = token.kind == lex::Kind::Bang
+                        && m == Match::BareDefs
+                        && tokens.get(ix + 1).is_some_and(|next| {
+                            !matches!(next.kind, lex::Kind::Space | lex::Kind::Newline)
+                        });
+                    if (is_amp || is_bare_def)
                         && token.range.len() == 1
-                        && (is_first || m == Match::Everywhere)
                         // &spec: ampersand can only start Amp at start or after a space
                         && last_was_space
                     {
                         self.start_new_group(State::Amp, tokens);
                         // &spec: as soon as we found a match, we allow matches everywhere
-                        m = Match::Everywhere;
+                        if m == Match::OnlyStart {
+                            m = Match::Everywhere;
+                        }
                     }
                     self.token_range.end += 1;
                 }
                 State::Amp => match token.kind {
                     lex::Kind::Ampersand => {
-                        self.start_new_group(State::Amp, tokens);
+                        // &spec: a single `&` at the end of an Amp marks a dependency and stays in the group
+                        let is_trailing = token.range.len() == 1
+                            && tokens.get(ix + 1).is_none_or(|next| {
+                                matches!(next.kind, lex::Kind::Space | lex::Kind::Newline)
+                            });
+                        if !is_trailing {
+                            self.start_new_group(State::Amp, tokens);
+                        }
                         self.token_range.end += 1;
                     }
                     lex::Kind::Space => {
@@ -312,6 +346,32 @@ mod tests {
                 "&!:prio:~priority",
                 "[!:prio:~priority]",
             ),
+            // Dependency
+            (&Match::Everywhere, "&todo&", "[todo&]"),
+            (&Match::Everywhere, "&a:b& c", "[a:b&]( c)"),
+            (&Match::Everywhere, "&a&b", "[a][b]"),
+            // Multi-colon
+            (&Match::Everywhere, "&a::b", "[a::b]"),
+            (&Match::Everywhere, "&a:::b", "[a::b]"),
+            (&Match::Everywhere, "&a::", "[a]"),
+            (&Match::Everywhere, "&::a:b", "[::a:b]"),
+            (&Match::Everywhere, "&!::a", "[!::a]"),
+            (&Match::Everywhere, "&a::b& c", "[a::b&]( c)"),
+            // KeyValue
+            (&Match::Everywhere, "&effort=3h", "[effort=3h]"),
+            (
+                &Match::Everywhere,
+                "&task:owner=alice",
+                "[task:owner=alice]",
+            ),
+            // Bare definitions
+            (&Match::Everywhere, "!:a:~date", "(!:a:~date)"),
+            (&Match::BareDefs, "!:a:~date", "[!:a:~date]"),
+            (&Match::BareDefs, "!todo &a", "[!todo]( )[a]"),
+            (&Match::BareDefs, "a !b", "(a )[!b]"),
+            (&Match::BareDefs, "a!b", "(a!b)"),
+            (&Match::BareDefs, "wow ! a", "(wow ! a)"),
+            (&Match::BareDefs, "!!a", "(!!a)"),
         ];
 
         let mut parser = Parser::new();
@@ -1,4 +1,4 @@
-use crate::amp;
+use crate::{amp, rank};
 use std::{cmp, collections, path};
 
 #[derive(Default)]
@@ -14,6 +14,14 @@ pub struct Location {
     pub org: String,
     pub ctx: String,
     pub prio: amp::Prio,
+    pub rank: rank::Rank,
+    pub layer: usize,       // Dependency layer from schedule::Schedule
+    pub order: Option<i64>, // Manual override via `&order=N`
+    pub due: Option<amp::Date>,
+    pub overdue: Option<i64>, // Days past `due` for open Nodes, relative to Env.today
+    pub effort: Option<amp::Effort>,
+    pub progress: String, // Aggregated data for Nodes with descendants
+    pub is_blocked: bool,
 }
 
 pub struct Meta {
@@ -23,7 +31,11 @@ pub struct Meta {
 
 pub enum By {
     Name,
+    // Highest prio first, file and line are used as tie-breaker
     Prio,
+    // Recommended order: prerequisites first, then manual order, highest prio and highest Rank.
+    // Remaining ties are broken by the earliest due date and smallest effort.
+    Rank,
 }
 
 impl Answer {
@@ -44,6 +56,7 @@ impl Answer {
         match by {
             By::Name => cmp = Self::by_name,
             By::Prio => cmp = Self::by_prio,
+            By::Rank => cmp = Self::by_rank,
         };
         self.locations.sort_by(|a, b| cmp(a, b));
     }
@@ -73,6 +86,25 @@ impl Answer {
         }
     }
 
+    fn by_rank(a: &Location, b: &Location) -> cmp::Ordering {
+        // Items without manual order, due date or effort come last
+        let key = |l: &Location| {
+            (
+                l.layer,
+                l.order.is_none(),
+                l.order,
+                l.prio.clone(),
+                cmp::Reverse(l.rank),
+                l.due.is_none(),
+                l.due.clone(),
+                l.effort.is_none(),
+                l.effort,
+            )
+        };
+        key(a)
+            .cmp(&key(b))
+            .then_with(|| (&a.filename, a.line_nr).cmp(&(&b.filename, b.line_nr)))
+    }
     fn by_prio(a: &Location, b: &Location) -> cmp::Ordering {
         let a = (&a.prio, &a.filename, a.line_nr);
         let b = (&b.prio, &b.filename, b.line_nr);
@@ -1,11 +1,12 @@
 pub mod show;
 
 use crate::{
+    amp,
     answer::{self, Answer},
     cli::show::Show,
-    config, fail, fs, path, query,
+    config, fail, forecast, fs, path, query,
     rubr::naft,
-    tree, util,
+    search, tree, util,
 };
 use std::io::Write;
 use tracing::{info, span, trace, Level};
@@ -21,9 +22,9 @@ impl App {
         let config = Config::load(cli_args)?;
 
         let app = App {
-            config,
             fs_forest: fs::Forest::new(),
-            builder: tree::builder::Builder::new(),
+            builder: tree::builder::Builder::new().with_env(config.env.clone()),
+            config,
         };
         Ok(app)
     }
@@ -41,33 +42,53 @@ impl App {
             Command::List => {
                 self.list_files_recursive_(&path::Path::root())?;
             }
-            Command::Search => {
-                let needle = self.config.what.clone();
-                println!("needle: {:?}", &needle);
+            Command::Search { is_regex } => {
+                let Some(needle) = &self.config.what else {
+                    fail!("Expected a search needle");
+                };
+                let needle = search::Needle::new(needle, *is_regex)?;
+
+                let forest = self.builder.create_forest_from(&mut self.fs_forest)?;
 
-                let _forest = self.builder.create_forest_from(&mut self.fs_forest)?;
+                answer = Some(search::search(&forest, &needle)?);
 
-                // &todo: Implement text-based search in all Part::Meta
+                if let Some(answer) = &mut answer {
+                    answer.order(&answer::By::Name);
+                    answer.show(&show::Display::All);
+                }
             }
             Command::Query(from) => {
                 let forest = self.builder.create_forest_from(&mut self.fs_forest)?;
 
-                let query = query::Query::try_from((&self.config.what, &self.config.args))?;
+                let query = self.config.query()?;
 
                 answer = Some(query::search(&forest, &query, from)?);
 
                 if let Some(answer) = &mut answer {
                     answer.order(&answer::By::Name);
                     answer.show(&show::Display::All);
+
+                    if let Some(needle) = query.needle.as_ref().filter(|_| answer.is_empty()) {
+                        let suggestions = query::suggest(&forest, needle, 5);
+                        if !suggestions.is_empty() {
+                            println!("Found no match for '{needle}', did you mean");
+                            for suggestion in &suggestions {
+                                println!("\t{suggestion}");
+                            }
+                        }
+                    }
                 }
             }
             Command::Next(cnt) => {
                 let forest = self.builder.create_forest_from(&mut self.fs_forest)?;
-                let query = query::Query::try_from((&self.config.what, &self.config.args))?;
+                let mut query = self.config.query()?;
+                // Closed items cannot be executed next, blocked items come after their prerequisites
+                query.schedule = true;
+                query.is_open.get_or_insert(true);
                 answer = Some(query::search(&forest, &query, &query::From::Org)?);
 
                 if let Some(answer) = &mut answer {
-                    answer.order(&answer::By::Prio);
+                    answer.order(&answer::By::Rank);
                     let display = match cnt {
                         None => show::Display::All,
                         Some(cnt) => show::Display::First(*cnt as u64 * 5),
@@ -75,6 +96,117 @@ impl App {
                     answer.show(&display);
                 }
             }
+            Command::Forecast => {
+                let forest = self.builder.create_forest_from(&mut self.fs_forest)?;
+                let query = self.config.query()?;
+
+                let today = &forest.env.today;
+                let calendar = &forest.env.calendar;
+                match forecast::Forecast::new(&forest, &query)? {
+                    None => println!("Found no open items to forecast"),
+                    Some(forecast) => {
+                        println!("Critical path");
+                        for step in &forecast.chain {
+                            let content: String = forest
+                                .trees
+                                .get(step.ix.tree_ix)
+                                .zip(forest.node(&step.ix))
+                                .map(|(tree, node)| node.content(tree))
+                                .unwrap_or_default();
+                            println!(
+                                "\t{}\t{}\t{}: {}",
+                                &step.end,
+                                step.estimate.to_string_with(calendar),
+                                forest.location(&step.ix),
+                                content.trim()
+                            );
+                        }
+                        print!(
+                            "Forecast: {} ({}",
+                            &forecast.end,
+                            forecast.total.to_string_with(calendar)
+                        );
+                        if forecast.spread.minutes() > 0 {
+                            print!(" ±{}", forecast.spread.to_string_with(calendar));
+                        }
+                        println!(" of work starting {})", today);
+                        if forecast.end_late != forecast.end {
+                            println!("84% likely by {}", &forecast.end_late);
+                        }
+                    }
+                }
+            }
+            Command::AssignId(location) => {
+                let forest = self.builder.create_forest_from(&mut self.fs_forest)?;
+
+                let Some((filename, line_nr)) = location
+                    .rsplit_once(':')
+                    .and_then(|(filename, line_nr)| Some((filename, line_nr.parse::<u64>().ok()?)))
+                else {
+                    fail!("Expected 'FILE:LINE', got '{}'", location);
+                };
+                let filename = std::fs::canonicalize(filename)?;
+
+                let Some(tree) = forest.trees.iter().find(|tree| {
+                    std::fs::canonicalize(&tree.filename).is_ok_and(|fp| fp == filename)
+                }) else {
+                    fail!("Could not find '{}' in the forest", filename.display());
+                };
+                if let tree::Format::MindMap = tree.format {
+                    // Tree.content holds the decoded node texts, writing it back would drop the XML
+                    fail!(
+                        "Assigning ids is not supported for mind-map '{}'",
+                        tree.filename.display()
+                    );
+                }
+                // A single line can result in several Nodes, eg for a Markdown bullet and its content
+                let nodes = tree
+                    .nodes
+                    .iter()
+                    .filter(|node| node.line_ix.map(|ix| ix + 1) == Some(line_nr))
+                    .collect::<Vec<_>>();
+                if nodes.is_empty() {
+                    fail!("Could not find item at '{}'", location);
+                }
+                if let Some(id) = nodes
+                    .iter()
+                    .find_map(|node| node.org.data.iter().find_map(|path| path.get_id()))
+                {
+                    fail!("Item at '{}' already has id {}", location, id);
+                }
+
+                let id = forest.next_id();
+                let Some(content) = tree.append_to_line(line_nr as usize - 1, &format!(" &{id}"))
+                else {
+                    fail!(
+                        "Could not find line {} in '{}'",
+                        line_nr,
+                        tree.filename.display()
+                    );
+                };
+                std::fs::write(&tree.filename, content)?;
+                println!("Assigned {} to {}", id, forest.location(&nodes[0].ix()));
+            }
+            Command::Lookup(id) => {
+                let forest = self.builder.create_forest_from(&mut self.fs_forest)?;
+
+                let Some(ix) = forest.find_id(id) else {
+                    fail!("Could not find item with id {}", id);
+                };
+                if let Some((tree, node)) = forest.trees.get(ix.tree_ix).zip(forest.node(ix)) {
+                    let mut lookup = Answer::new();
+                    lookup.add(answer::Location {
+                        filename: tree.filename.clone(),
+                        line_nr: node.line_ix.unwrap_or(0) + 1,
+                        content: node.content(tree),
+                        org: node.org.to_string_with(&forest.env.calendar),
+                        ctx: node.ctx.to_string_with(&forest.env.calendar),
+                        ..Default::default()
+                    });
+                    lookup.show(&show::Display::All);
+                    answer = Some(lookup);
+                }
+            }
             Command::Debug => {
                 let needle = &self.config.what;
 
@@ -102,13 +234,9 @@ impl App {
                                 let answer = answer.as_mut().unwrap();
 
                                 if tree.filename.is_file() {
-                                    let content = node
-                                        .parts
-                                        .iter()
-                                        .filter_map(|part| tree.content.get(part.range.clone()))
-                                        .collect();
-                                    let org = node.org.to_string();
-                                    let ctx = node.ctx.to_string();
+                                    let content = node.content(tree);
+                                    let org = node.org.to_string_with(&forest.env.calendar);
+                                    let ctx = node.ctx.to_string_with(&forest.env.calendar);
 
                                     answer.add(answer::Location {
                                         filename: tree.filename.clone(),
@@ -138,7 +266,12 @@ impl App {
 
                 println!("Forest:defs");
                 for path in &forest.defs.data {
-                    println!("\t{path}");
+                    println!("\t{}", path.to_string_with(&forest.env.calendar));
+                }
+
+                println!("Statuses");
+                for status_def in &forest.env.statuses.data {
+                    println!("\t{status_def}");
                 }
             }
         }
@@ -186,9 +319,12 @@ enum Command {
     None,
     Query(query::From),
     Next(Option<u8>),
-    Search,
+    Search { is_regex: bool },
     List,
     Debug,
+    Forecast,
+    AssignId(String),
+    Lookup(amp::Id),
 }
 
 #[derive(Debug, Clone)]
@@ -196,12 +332,23 @@ struct Config {
     global: config::Global,
     command: Command,
     do_open: bool,
+    fuzzy: bool,
     what: Option<String>,
     args: Vec<String>,
     groves: Vec<config::Grove>,
+    env: amp::Env,
 }
 
 impl Config {
+    // Query from the `what` and `where` arguments
+    fn query(&self) -> util::Result<query::Query> {
+        let mut query = query::Query::try_from((&self.what, &self.args))?;
+        if self.fuzzy {
+            query.make_fuzzy();
+        }
+        Ok(query)
+    }
+
     fn load(mut cli_args: config::CliArgs) -> util::Result<Config> {
         let span = span!(Level::INFO, "Config.load");
         let _s = span.enter();
@@ -243,12 +390,21 @@ impl Config {
                 if !cli_args.search {
                     cli_args.search = command.search;
                 }
+                if !cli_args.fuzzy {
+                    cli_args.fuzzy = command.fuzzy;
+                }
+                if !cli_args.regex {
+                    cli_args.regex = command.regex;
+                }
                 if !cli_args.list {
                     cli_args.list = command.list;
                 }
                 if !cli_args.debug {
                     cli_args.debug = command.debug;
                 }
+                if !cli_args.forecast {
+                    cli_args.forecast = command.forecast;
+                }
                 if cli_args.what.is_none() {
                     cli_args.what = command.what.clone();
                 }
@@ -327,8 +483,11 @@ impl Config {
                         query_ctx: cli_args.query_ctx,
                         next_all: cli_args.next_all,
                         search: cli_args.search,
+                        regex: cli_args.regex,
+                        fuzzy: cli_args.fuzzy,
                         list: cli_args.list,
                         debug: cli_args.debug,
+                        forecast: cli_args.forecast,
                         what: cli_args.what.clone(),
                     };
 
@@ -347,6 +506,47 @@ impl Config {
             }
         }
 
+        let mut env = amp::Env::default();
+
+        if let Some(calendar) = &config_global.calendar {
+            let holidays = calendar
+                .holidays
+                .iter()
+                .map(|holiday| amp::Date::try_from(holiday.as_str()))
+                .collect::<util::Result<Vec<amp::Date>>>()?;
+            env.calendar = amp::Calendar {
+                hours_per_day: calendar.hours_per_day,
+                days_per_week: calendar.days_per_week,
+                holidays: holidays.clone(),
+            };
+            for (name, worker) in &calendar.workers {
+                env.workers.insert(
+                    name.clone(),
+                    amp::Calendar {
+                        hours_per_day: worker.hours_per_day,
+                        days_per_week: worker.days_per_week,
+                        holidays: holidays.clone(),
+                    },
+                );
+            }
+        }
+
+        // Configured Statuses extend the built-in Statuses
+        for status in config_global.statuses.iter().flatten() {
+            let transitions = status
+                .transitions
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>();
+            env.statuses
+                .insert(amp::StatusDef::new(&status.name, status.open, &transitions));
+        }
+
+        if let Some(now) = &cli_args.now {
+            env.today = amp::Date::try_from(now.as_str())?;
+            info!("Using {} as reference date", &env.today);
+        }
+
         let mut groves = Vec::new();
         {
             for grove_str in &cli_args.grove {
@@ -383,11 +583,23 @@ impl Config {
         } else if cli_args.next_all {
             Command::Next(None)
         } else if cli_args.search {
-            Command::Search
+            Command::Search {
+                is_regex: cli_args.regex,
+            }
         } else if cli_args.list {
             Command::List
         } else if cli_args.debug {
             Command::Debug
+        } else if cli_args.forecast {
+            Command::Forecast
+        } else if let Some(location) = &cli_args.assign_id {
+            Command::AssignId(location.clone())
+        } else if let Some(id) = cli_args
+            .what
+            .as_deref()
+            .and_then(|what| amp::Id::try_from(what).ok())
+        {
+            Command::Lookup(id)
         } else {
             Command::None
         };
@@ -396,9 +608,11 @@ impl Config {
             global: config_global,
             command,
             do_open: cli_args.open,
+            fuzzy: cli_args.fuzzy,
             what: cli_args.what.clone(),
             args: cli_args.wher.clone(),
             groves,
+            env,
         };
 
         Ok(config)
@@ -44,9 +44,10 @@ impl Show for answer::Answer {
                     // };
 
                     let mut os = String::new();
+                    let mark = if location.is_blocked { 'B' } else { ' ' };
                     write!(
                         os,
-                        "  {}\t{:org_width$}\t{:ctx_width$}\t{:>4}: {}",
+                        "{mark} {}\t{:org_width$}\t{:ctx_width$}\t{:>4}: {}",
                         &location.prio,
                         &location.org,
                         &location.ctx,
@@ -54,6 +55,12 @@ impl Show for answer::Answer {
                         &location.content
                     )
                     .unwrap();
+                    if !location.progress.is_empty() {
+                        write!(os, " [{}]", &location.progress).unwrap();
+                    }
+                    if let Some(days) = location.overdue {
+                        write!(os, " ({days}d overdue)").unwrap();
+                    }
 
                     let color;
                     if true {
@@ -114,7 +121,10 @@ impl Show for answer::Answer {
                         color = colored::Color::from(color_str);
                     }
 
-                    let os = os.color(color);
+                    let mut os = os.color(color);
+                    if location.is_blocked {
+                        os = os.dimmed();
+                    }
                     println!("{}", &os);
                 }
             });
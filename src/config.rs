@@ -70,10 +70,18 @@ pub struct CliArgs {
     #[arg(short = 'N', long, default_value_t = false)]
     pub next_all: bool,
 
-    /// Free-form search in all metadata
+    /// Case-insensitive free-text search for `WHAT` in the content of all items
     #[arg(short = 's', long, default_value_t = false)]
     pub search: bool,
 
+    /// Interpret the `--search` needle as a regular expression
+    #[arg(short = 'e', long, default_value_t = false)]
+    pub regex: bool,
+
+    /// Let query Tags also match with Tags that are a few edits away
+    #[arg(short = 'z', long, default_value_t = false)]
+    pub fuzzy: bool,
+
     /// List all files in the forest
     #[arg(short = 'l', long, default_value_t = false)]
     pub list: bool,
@@ -82,11 +90,23 @@ pub struct CliArgs {
     #[arg(short = 'd', long, default_value_t = false)]
     pub debug: bool,
 
+    /// Forecast the completion date of the open items below `WHAT` via their critical path
+    #[arg(short = 'F', long, default_value_t = false)]
+    pub forecast: bool,
+
+    /// Assign the next free `&#N` id to the item at `FILE:LINE`, rewriting the file in place
+    #[arg(short = 'I', long, value_name = "FILE:LINE")]
+    pub assign_id: Option<String>,
+
+    /// Reference date for relative dates like `today` or `+3d`, defaults to the system date
+    #[arg(long, value_name = "DATE")]
+    pub now: Option<String>,
+
     /// Where
     #[arg(short = 'w', long)]
     pub wher: Vec<String>,
 
-    /// Item to search
+    /// Item to search, `'#N'` looks up the item with that id (quoted, as `#` starts a shell comment)
     pub what: Option<String>,
 }
 pub fn default_verbose() -> u32 {
@@ -106,6 +126,8 @@ pub struct Global {
     pub path: Option<path::PathBuf>,
     pub groves: Vec<Grove>,
     pub commands: Vec<Command>,
+    pub calendar: Option<Calendar>,
+    pub statuses: Option<Vec<Status>>,
 }
 
 #[derive(serde::Deserialize, Debug, Clone)]
@@ -130,6 +152,45 @@ fn default_true() -> bool {
     true
 }
 
+// Work calendar, loaded from '$config_root/calendar.toml'
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct Calendar {
+    #[serde(default = "default_hours_per_day")]
+    pub hours_per_day: u32,
+    #[serde(default = "default_days_per_week")]
+    pub days_per_week: u32,
+    // Dates in `YYYY-MM-DD` format
+    #[serde(default)]
+    pub holidays: Vec<String>,
+    // Own Calendars of members that work less, eg `[workers.alice]` with `days_per_week = 3`.
+    // The holidays of the team apply to them as well.
+    #[serde(default)]
+    pub workers: std::collections::BTreeMap<String, Calendar>,
+}
+fn default_hours_per_day() -> u32 {
+    8
+}
+fn default_days_per_week() -> u32 {
+    5
+}
+
+// Status definitions, loaded from '$config_root/statuses.toml'
+// These extend the built-in Statuses, a Status with the same name overrides its openness
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct Statuses {
+    pub status: Vec<Status>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct Status {
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub open: bool,
+    // Statuses this Status can move to, all transitions are allowed when empty
+    #[serde(default)]
+    pub transitions: Vec<String>,
+}
+
 #[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
 pub struct Commands {
     pub command: Vec<Command>,
@@ -152,8 +213,14 @@ pub struct Command {
     pub query_ctx: bool,
     pub next_all: bool,
     pub search: bool,
+    #[serde(default)]
+    pub regex: bool,
+    #[serde(default)]
+    pub fuzzy: bool,
     pub list: bool,
     pub debug: bool,
+    #[serde(default)]
+    pub forecast: bool,
 }
 
 impl naft::ToNaft for Command {
@@ -169,8 +236,11 @@ impl naft::ToNaft for Command {
         b.attr("query_ctx", &self.query_ctx)?;
         b.attr("next_all", &self.next_all)?;
         b.attr("search", &self.search)?;
+        b.attr("regex", &self.regex)?;
+        b.attr("fuzzy", &self.fuzzy)?;
         b.attr("list", &self.list)?;
         b.attr("debug", &self.debug)?;
+        b.attr("forecast", &self.forecast)?;
         if let Some(what) = &self.what {
             b.attr("what", what)?;
         }
@@ -295,6 +365,63 @@ impl Global {
             }
         }
 
+        {
+            let calendar_fp = config_root.join("calendar.toml");
+            if calendar_fp.is_file() {
+                info!("Loading calendar from '{}'", calendar_fp.display());
+                let content = std::fs::read(&calendar_fp)?;
+                let content = std::str::from_utf8(&content)?;
+                trace!("calendar content :\n{content}");
+                match toml::from_str::<Calendar>(content) {
+                    Ok(calendar) => {
+                        for calendar in std::iter::once(&calendar).chain(calendar.workers.values())
+                        {
+                            if !(1..=24).contains(&calendar.hours_per_day) {
+                                fail!(
+                                    "Calendar hours_per_day should be in [1, 24] in '{}'",
+                                    calendar_fp.display()
+                                );
+                            }
+                            if !(1..=7).contains(&calendar.days_per_week) {
+                                fail!(
+                                    "Calendar days_per_week should be in [1, 7] in '{}'",
+                                    calendar_fp.display()
+                                );
+                            }
+                        }
+                        global.calendar = Some(calendar);
+                    }
+                    Err(err) => {
+                        fail!(
+                            "Could not parse calendar from '{}': {}",
+                            calendar_fp.display(),
+                            err
+                        );
+                    }
+                }
+            }
+        }
+
+        {
+            let statuses_fp = config_root.join("statuses.toml");
+            if statuses_fp.is_file() {
+                info!("Loading statuses from '{}'", statuses_fp.display());
+                let content = std::fs::read(&statuses_fp)?;
+                let content = std::str::from_utf8(&content)?;
+                trace!("statuses content :\n{content}");
+                match toml::from_str::<Statuses>(content) {
+                    Ok(statuses) => global.statuses = Some(statuses.status),
+                    Err(err) => {
+                        fail!(
+                            "Could not parse statuses from '{}': {}",
+                            statuses_fp.display(),
+                            err
+                        );
+                    }
+                }
+            }
+        }
+
         Ok(global)
     }
 }
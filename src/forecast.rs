@@ -0,0 +1,310 @@
+use crate::{amp, fail, query, tree, util};
+use std::collections;
+
+// Completion forecast for the open items matching a Query, based on their critical path.
+// The critical path is the chain of prerequisites with the largest total expected Duration, assuming
+// independent items are executed in parallel.
+#[derive(Debug)]
+pub struct Forecast {
+    pub total: amp::Duration, // Expected Duration of the critical path, in time of the team Calendar
+    pub spread: amp::Duration, // Standard deviation of `total`, derived from the Estimates
+    pub end: amp::Date,
+    pub end_late: amp::Date, // End date for `total + spread`, which is met with a probability of about 84%
+    pub chain: Vec<Step>,    // Critical path, starting with the first item to execute
+}
+
+#[derive(Debug)]
+pub struct Step {
+    pub ix: tree::NodeIx,
+    pub estimate: amp::Estimate,
+    pub end: amp::Date,
+}
+
+// Expected finish time in minutes of work, and the prerequisite that drives it
+type Finish = (u32, Option<tree::NodeIx>);
+
+impl Forecast {
+    // Open items are searched in both Node.def and Node.ctx to include all items below a project definition.
+    // Returns None when no open item matches `query`.
+    // Work starts on Env.today and is planned according to Env.calendar from `forest`.
+    // Items with `&worker=NAME` take longer when NAME has a smaller week in Env.workers.
+    pub fn new(forest: &tree::Forest, query: &query::Query) -> util::Result<Option<Forecast>> {
+        let today = &forest.env.today;
+        let calendar = &forest.env.calendar;
+        let mut finishes = collections::BTreeMap::<tree::NodeIx, Finish>::new();
+        let mut last: Option<(u32, tree::NodeIx)> = None;
+
+        for tree in &forest.trees {
+            for node in &tree.nodes {
+                let is_match = query.matches(node, &query::From::Def, &forest.env)
+                    || query.matches(node, &query::From::Ctx, &forest.env);
+                if node.is_open() && is_match {
+                    let ix = node.ix();
+                    let (minutes, _) = finish(forest, &ix, &mut finishes, &mut Vec::new())?;
+                    if last.is_none_or(|(last_minutes, _)| minutes > last_minutes) {
+                        last = Some((minutes, ix));
+                    }
+                }
+            }
+        }
+
+        let Some((total, last_ix)) = last else {
+            return Ok(None);
+        };
+
+        let to_date = |minutes| {
+            calendar.add_duration(
+                today,
+                &amp::Duration::from_calendar(0, 0, 0, minutes, calendar),
+            )
+        };
+
+        let mut chain = Vec::new();
+        let mut variance = 0.0;
+        let mut ix_opt = Some(last_ix);
+        while let Some(ix) = ix_opt {
+            let (minutes, prereq) = finishes[&ix];
+            let estimate = estimate(forest, &ix);
+            variance += estimate.variance();
+            chain.push(Step {
+                ix,
+                estimate,
+                end: to_date(minutes),
+            });
+            ix_opt = prereq;
+        }
+        chain.reverse();
+
+        let spread = variance.sqrt().round() as u32;
+        Ok(Some(Forecast {
+            total: amp::Duration::from_calendar(0, 0, 0, total, calendar),
+            spread: amp::Duration::from_calendar(0, 0, 0, spread, calendar),
+            end: to_date(total),
+            end_late: to_date(total + spread),
+            chain,
+        }))
+    }
+}
+
+// The Estimate specified on the item itself, inherited Estimates are not taken into account
+fn estimate(forest: &tree::Forest, ix: &tree::NodeIx) -> amp::Estimate {
+    forest
+        .node(ix)
+        .and_then(|node| node.org.data.iter().find_map(|path| path.get_estimate()))
+        .unwrap_or_default()
+}
+
+// Minutes of the team Calendar needed for the expected work of the item itself, eg a worker with
+// half a week needs 2d of team time for 1d of work
+fn elapsed(forest: &tree::Forest, ix: &tree::NodeIx) -> u32 {
+    let minutes = estimate(forest, ix).expected().minutes();
+    let worker = forest.node(ix).and_then(|node| {
+        node.ctx
+            .data
+            .iter()
+            .find_map(|path| path.get_value("worker"))
+    });
+    let team = &forest.env.calendar;
+    let calendar = forest.env.worker_calendar(worker);
+    (minutes as u64 * team.minutes_per_week() as u64 / calendar.minutes_per_week() as u64) as u32
+}
+
+fn finish(
+    forest: &tree::Forest,
+    ix: &tree::NodeIx,
+    finishes: &mut collections::BTreeMap<tree::NodeIx, Finish>,
+    stack: &mut Vec<tree::NodeIx>,
+) -> util::Result<Finish> {
+    if let Some(finish) = finishes.get(ix) {
+        return Ok(*finish);
+    }
+    if stack.contains(ix) {
+        fail!("Found dependency cycle at '{}'", forest.location(ix));
+    }
+
+    let mut start: Finish = (0, None);
+    if let Some(node) = forest.node(ix) {
+        stack.push(*ix);
+        for prereq_ix in &node.prereqs {
+            // Closed prerequisites do not require more work
+            if forest
+                .node(prereq_ix)
+                .is_some_and(|prereq| prereq.is_open())
+            {
+                let (minutes, _) = finish(forest, prereq_ix, finishes, stack)?;
+                if start.1.is_none() || minutes > start.0 {
+                    start = (minutes, Some(*prereq_ix));
+                }
+            }
+        }
+        stack.pop();
+    }
+
+    let finish = (start.0 + elapsed(forest, ix), start.1);
+    finishes.insert(*ix, finish);
+    Ok(finish)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::builder;
+
+    #[test]
+    fn test_forecast() -> util::Result<()> {
+        let calendar = amp::Calendar {
+            hours_per_day: 8,
+            days_per_week: 5,
+            holidays: vec![],
+        };
+        // Monday
+        let today = amp::Date::new(2024, 9, 30);
+        let mut builder = builder::Builder::new().with_env(amp::Env {
+            today,
+            calendar: calendar.clone(),
+            ..Default::default()
+        });
+        let forest = builder.create_forest_from_md(&[
+            "# Plan &!:status:~status\n- &!:est:~duration\n## Project &!:proj\n\
+            - A &!:a &todo &est:1d\n\
+            - B &!:b &todo &est:2d &a&\n\
+            - C &todo &est:1d &a&\n\
+            - D &todo &est:1d &b&\n\
+            - E &!:e &done &est:5d\n\
+            - F &todo &est:4h &e&\n\
+            ## Other\n- G &todo &est:2w",
+        ])?;
+
+        let query = query::Query::try_from((&Some("proj".to_string()), &vec![]))?;
+        let forecast = Forecast::new(&forest, &query)?.unwrap();
+
+        assert_eq!(
+            forecast.total,
+            amp::Duration::from_calendar(0, 4, 0, 0, &calendar)
+        );
+        assert_eq!(forecast.end, amp::Date::new(2024, 10, 3));
+        let chain = forecast
+            .chain
+            .iter()
+            .map(|step| (forest.location(&step.ix), step.end.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            chain,
+            vec![
+                ("0.md:4".to_string(), "2024-09-30".to_string()),
+                ("0.md:5".to_string(), "2024-10-02".to_string()),
+                ("0.md:7".to_string(), "2024-10-03".to_string()),
+            ]
+        );
+
+        let query = query::Query::try_from((&Some("unknown".to_string()), &vec![]))?;
+        assert!(Forecast::new(&forest, &query)?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_forecast_estimates() -> util::Result<()> {
+        let calendar = amp::Calendar {
+            hours_per_day: 8,
+            days_per_week: 5,
+            holidays: vec![],
+        };
+
+        let mut builder = builder::Builder::new().with_env(amp::Env {
+            today: amp::Date::new(2024, 9, 30),
+            calendar: calendar.clone(),
+            ..Default::default()
+        });
+        let forest = builder.create_forest_from_md(&["# Plan &!:est:~duration
+## Project &!:proj
+            - A &!:a &todo &est:1d..3d
+            - B &todo &est:1d..2d..3d &a&
+            - C &todo &est:1h &a&"])?;
+
+        let query = query::Query::try_from((&Some("proj".to_string()), &vec![]))?;
+        let forecast = Forecast::new(&forest, &query)?.unwrap();
+
+        // Expected 2d + 2d, each with a standard deviation of 1/3d: sqrt(2)/3d
+        assert_eq!(
+            forecast.total,
+            amp::Duration::from_calendar(0, 4, 0, 0, &calendar)
+        );
+        assert_eq!(
+            forecast.spread,
+            amp::Duration::from_calendar(0, 0, 0, 226, &calendar)
+        );
+        assert_eq!(forecast.end, amp::Date::new(2024, 10, 3));
+        assert_eq!(forecast.end_late, amp::Date::new(2024, 10, 4));
+        assert_eq!(forecast.chain.len(), 2);
+        assert_eq!(
+            forecast.chain[0].estimate.to_string_with(&calendar),
+            "1d..3d"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_forecast_calendar() -> util::Result<()> {
+        // Part-time: 4h per day, Monday to Wednesday
+        let calendar = amp::Calendar {
+            hours_per_day: 4,
+            days_per_week: 3,
+            holidays: vec![],
+        };
+        let mut builder = builder::Builder::new().with_env(amp::Env {
+            today: amp::Date::new(2024, 9, 30),
+            calendar: calendar.clone(),
+            ..Default::default()
+        });
+        let forest = builder.create_forest_from_md(&["# Plan &!:est:~duration
+## Project &!:proj
+            - A &!:a &todo &est:1w
+            - B &todo &est:2d &a&"])?;
+
+        let query = query::Query::try_from((&Some("proj".to_string()), &vec![]))?;
+        let forecast = Forecast::new(&forest, &query)?.unwrap();
+
+        assert_eq!(forecast.total.minutes(), 5 * 4 * 60);
+        assert_eq!(forecast.total.to_string_with(&calendar), "1w2d");
+        assert_eq!(forecast.end, amp::Date::new(2024, 10, 8));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_forecast_workers() -> util::Result<()> {
+        let calendar = amp::Calendar {
+            hours_per_day: 8,
+            days_per_week: 4,
+            holidays: vec![],
+        };
+        // Part-time: 4h per day, 2 days per week
+        let part_time = amp::Calendar {
+            hours_per_day: 4,
+            days_per_week: 2,
+            holidays: vec![],
+        };
+        let mut builder = builder::Builder::new().with_env(amp::Env {
+            today: amp::Date::new(2024, 9, 30),
+            calendar: calendar.clone(),
+            workers: [("alice".to_string(), part_time)].into(),
+            ..Default::default()
+        });
+        let forest = builder.create_forest_from_md(&["# Plan &!:est:~duration
+## Project &!:proj
+            - A &!:a &todo &est:1d &worker=Alice
+            - B &todo &est:2d &a& &worker=bob"])?;
+
+        let query = query::Query::try_from((&Some("proj".to_string()), &vec![]))?;
+        let forecast = Forecast::new(&forest, &query)?.unwrap();
+
+        // 1d of work for Alice takes 4 days of team time, Bob has no own Calendar
+        assert_eq!(forecast.total.to_string_with(&calendar), "1w2d");
+        assert_eq!(forecast.chain[0].end, amp::Date::new(2024, 10, 3));
+        assert_eq!(forecast.end, amp::Date::new(2024, 10, 8));
+
+        Ok(())
+    }
+}
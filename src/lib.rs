@@ -3,11 +3,15 @@ mod amp;
 mod answer;
 pub mod cli;
 pub mod config;
+mod forecast;
 mod fs;
 mod ignore;
 mod lex;
 mod path;
 mod query;
+mod rank;
 mod rubr;
+mod schedule;
+mod search;
 mod tree;
 pub mod util;
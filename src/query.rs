@@ -1,59 +1,230 @@
-use crate::{amp, answer, fail, rubr::naft, tree, util};
-use tracing::{info, trace};
+pub mod expr;
+
+use crate::{
+    amp, answer, rank,
+    rubr::{fuzz, naft},
+    schedule, tree, util,
+};
+use tracing::{info, trace, warn};
 
 #[derive(Debug, Default)]
 pub struct Query {
-    pub needle: Option<amp::Path>,
-    pub constraints: Vec<amp::Path>,
+    pub needle: Option<expr::Expr>,
+    pub constraints: Vec<expr::Expr>,
+    // Compute the dependency layers for the recommended order
+    pub schedule: bool,
+    // Only keep Nodes with an open (`~open`) or closed (`~closed`) Status
+    pub is_open: Option<bool>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum From {
     Org,
     Ctx,
+    Def, // The definition a Node belongs to, including inherited definitions
 }
 
-// `from` determines where `query.needle` is searched.
-// `query.constraints` are always searched in `Node.ctx`
-pub fn search(forest: &tree::Forest, query: &Query, from: &From) -> util::Result<answer::Answer> {
-    let mut answer = answer::Answer::new();
+impl Query {
+    // `from` determines where `self.needle` is searched.
+    // `self.constraints` are always searched in `Node.ctx`
+    pub fn matches(&self, node: &tree::Node, from: &From, env: &amp::Env) -> bool {
+        let mut is_match = match &self.needle {
+            Some(needle) => needle.matches(node, from, env),
+            None => match from {
+                From::Org => !node.org.is_empty(),
+                From::Ctx => !node.ctx.is_empty(),
+                From::Def => node.def.is_some(),
+            },
+        };
 
-    forest.dfs(|tree, node| {
-        let mut is_match;
-        {
-            let paths = match from {
-                From::Org => &node.org,
-                From::Ctx => &node.ctx,
-            };
-
-            is_match = match &query.needle {
-                Some(needle) => paths.matches_with(needle),
-                _ => !paths.is_empty(),
-            };
+        for constraint in &self.constraints {
+            if !constraint.matches(node, &From::Ctx, env) {
+                is_match = false;
+            }
         }
 
-        for constraint in &query.constraints {
-            if !node.ctx.matches_with(constraint) {
+        if let Some(is_open) = self.is_open {
+            if node.is_open() != is_open {
                 is_match = false;
             }
         }
 
-        if is_match {
-            let content = node
+        is_match
+    }
+
+    // Candidate Nodes from `index` that might match, None when all Nodes have to be checked
+    pub fn candidates(
+        &self,
+        index: &tree::index::Index,
+        from: &From,
+    ) -> Option<std::collections::BTreeSet<tree::NodeIx>> {
+        let needle = self.needle.as_ref()?.candidates(index, from);
+        let constraints = self
+            .constraints
+            .iter()
+            .map(|constraint| constraint.candidates(index, &From::Ctx));
+        std::iter::once(needle)
+            .chain(constraints)
+            .flatten()
+            .reduce(|lhs, rhs| lhs.intersection(&rhs).copied().collect())
+    }
+
+    // Lets the Tags from the needle and constraints match with Tags that are a few edits away
+    pub fn make_fuzzy(&mut self) {
+        let fuzzy = |expr: expr::Expr| match expr {
+            expr::Expr::Fuzzy(_) => expr,
+            _ => expr::Expr::Fuzzy(Box::new(expr)),
+        };
+        self.needle = self.needle.take().map(fuzzy);
+        self.constraints = std::mem::take(&mut self.constraints)
+            .into_iter()
+            .map(fuzzy)
+            .collect();
+    }
+}
+
+// Up to `max` definitions from `forest.defs` that resemble one of the Paths in `needle`, closest first.
+// Each Part of a needle Path is matched with its closest def Part via fuzz::distance(), like the LSP workspace symbols.
+pub fn suggest(forest: &tree::Forest, needle: &expr::Expr, max: usize) -> Vec<amp::Path> {
+    let needle_paths = needle
+        .paths()
+        .iter()
+        .map(|path| {
+            path.parts
+                .iter()
+                .filter_map(|part| match part {
+                    amp::Part::Gap => None,
+                    // Values are not part of the defs
+                    amp::Part::KeyValue(kv) => Some(kv.0.clone()),
+                    _ => Some(part.to_string()),
+                })
+                .collect::<Vec<_>>()
+        })
+        .filter(|parts| !parts.is_empty())
+        .collect::<Vec<_>>();
+
+    // Sum of the distances of the needle Parts, None when some Part cannot be matched
+    let score = |needle_parts: &Vec<String>, def_parts: &Vec<String>| -> Option<f64> {
+        let mut sum = 0.0;
+        for needle_part in needle_parts {
+            // Parts that are not fully contained should at least be a few edits away
+            sum += def_parts
+                .iter()
+                .filter_map(|def_part| {
+                    let (distance, skip_count) = fuzz::distance(needle_part, def_part);
+                    (skip_count == 0 || amp::is_similar_text(def_part, needle_part))
+                        .then_some(distance)
+                })
+                .min_by(f64::total_cmp)?;
+        }
+        Some(sum)
+    };
+
+    let mut scored = forest
+        .defs
+        .data
+        .iter()
+        .filter_map(|def| {
+            let def_parts = def
                 .parts
                 .iter()
-                .filter_map(|part| tree.content.get(part.range.clone()))
-                .collect();
-            let org = node.org.to_string();
-            let ctx = node.ctx.to_string();
+                .map(|part| part.to_string())
+                .collect::<Vec<_>>();
+            needle_paths
+                .iter()
+                .filter_map(|needle_parts| score(needle_parts, &def_parts))
+                .min_by(f64::total_cmp)
+                .map(|score| (score, def))
+        })
+        .collect::<Vec<_>>();
+    scored.sort_by(|(lhs, lhs_def), (rhs, rhs_def)| lhs.total_cmp(rhs).then(lhs_def.cmp(rhs_def)));
+
+    scored
+        .into_iter()
+        .take(max)
+        .map(|(_, def)| {
+            let mut def = def.clone();
+            def.is_definition = false;
+            def
+        })
+        .collect()
+}
+
+// `from` determines where `query.needle` is searched.
+// `query.constraints` are always searched in `Node.ctx`
+pub fn search(forest: &tree::Forest, query: &Query, from: &From) -> util::Result<answer::Answer> {
+    let mut answer = answer::Answer::new();
+
+    let schedule = query.schedule.then(|| schedule::Schedule::new(forest));
+    // An order that ignores a dependency cycle would be misleading
+    if let Some(schedule) = &schedule {
+        schedule.check_cycles(forest)?;
+    }
+
+    // Nodes outside the candidates from the index cannot match, only these are visited
+    // The resulting order depends on this, callers sort the Answer
+    let candidates = query.candidates(&forest.index, from);
+    if let Some(candidates) = &candidates {
+        trace!(
+            "Index reduced the search to {} candidates",
+            candidates.len()
+        );
+    }
+
+    let mut visit = |tree: &tree::Tree, node: &tree::Node| -> util::Result<()> {
+        let is_match = query.matches(node, from, &forest.env);
+
+        let is_blocked = is_match && forest.is_blocked(node);
+
+        if is_match {
+            let content = node.content(tree);
+            let org = node.org.to_string_with(&forest.env.calendar);
+            let ctx = node.ctx.to_string_with(&forest.env.calendar);
             // info!("{} org {} ctx {}", tree.filename.display(), &org, &ctx);
-            let prio = node
+            let prio = node.prio();
+            let rank = rank::Rank::from_paths(&node.ctx);
+            let progress = if node.is_leaf() {
+                String::new()
+            } else {
+                node.agg.to_string_with(&forest.env.calendar)
+            };
+            let layer = schedule
+                .as_ref()
+                .map(|schedule| schedule.layer(&node.ix()))
+                .unwrap_or(0);
+            let order = node
                 .ctx
                 .data
                 .iter()
-                .filter_map(|path| path.get_prio().map(Clone::clone))
-                .next()
-                .unwrap_or_else(|| amp::Prio::new(5, 0));
+                .find_map(|path| path.get_value("order"))
+                .and_then(|value| match value.parse::<i64>() {
+                    Ok(order) => Some(order),
+                    Err(_) => {
+                        warn!(
+                            "Invalid order '{}' for '{}:{}'",
+                            value,
+                            tree.filename.display(),
+                            node.line_ix.unwrap_or(0) + 1
+                        );
+                        None
+                    }
+                });
+            let due = node
+                .ctx
+                .data
+                .iter()
+                .filter(|path| path.has_tag("due"))
+                .find_map(|path| path.get_date().cloned());
+            let overdue = due
+                .as_ref()
+                .filter(|_| node.is_open())
+                .map(|due| forest.env.today.days_since(due))
+                .filter(|days| *days > 0);
+            let effort = node
+                .ctx
+                .data
+                .iter()
+                .find_map(|path| path.get_effort().copied());
 
             answer.add(answer::Location {
                 filename: tree.filename.clone(),
@@ -63,10 +234,29 @@ pub fn search(forest: &tree::Forest, query: &Query, from: &From) -> util::Result
                 ctx,
                 content,
                 prio,
+                rank,
+                layer,
+                order,
+                due,
+                overdue,
+                effort,
+                progress,
+                is_blocked,
             });
         }
         Ok(())
-    })?;
+    };
+
+    match &candidates {
+        Some(candidates) => {
+            for ix in candidates {
+                if let Some((tree, node)) = forest.trees.get(ix.tree_ix).zip(forest.node(ix)) {
+                    visit(tree, node)?;
+                }
+            }
+        }
+        None => forest.dfs(&mut visit)?,
+    }
 
     Ok(answer)
 }
@@ -75,12 +265,10 @@ impl naft::ToNaft for Query {
     fn to_naft(&self, b: &mut naft::Body<'_, '_>) -> std::fmt::Result {
         b.node(&"Node")?;
         if let Some(needle) = &self.needle {
-            b.set_ctx("needle");
-            needle.to_naft(b)?;
+            b.attr("needle", needle)?;
         }
         for constraint in &self.constraints {
-            b.set_ctx("constraint");
-            constraint.to_naft(b)?;
+            b.attr("constraint", constraint)?;
         }
         Ok(())
     }
@@ -91,9 +279,7 @@ impl TryFrom<(&Option<String>, &Vec<String>)> for Query {
     type Error = util::ErrorType;
 
     fn try_from(args: (&Option<String>, &Vec<String>)) -> util::Result<Query> {
-        let mut amp_parser = amp::parse::Parser::new();
-
-        let needle: Option<amp::Path>;
+        let needle: Option<expr::Expr>;
         {
             if let Some(needle_str) = args.0 {
                 match needle_str.as_str() {
@@ -102,18 +288,7 @@ impl TryFrom<(&Option<String>, &Vec<String>)> for Query {
                         trace!("Found wildcard '{}' for needle", needle_str);
                         needle = None;
                     }
-                    _ => {
-                        amp_parser
-                            .parse(&format!("&{needle_str}"), &amp::parse::Match::OnlyStart)?;
-                        if let Some(stmt) = amp_parser.stmts.first() {
-                            match &stmt.kind {
-                                amp::parse::Kind::Amp(kv) => needle = Some(kv.clone()),
-                                _ => fail!("Expected to find AMP"),
-                            }
-                        } else {
-                            fail!("Expected to find at least one statement");
-                        }
-                    }
+                    _ => needle = Some(expr::Expr::try_from(needle_str.as_str())?),
                 }
             } else {
                 needle = None;
@@ -121,27 +296,144 @@ impl TryFrom<(&Option<String>, &Vec<String>)> for Query {
         }
         trace!("needle: {:?}", needle);
 
-        let mut constraints = Vec::<amp::Path>::new();
+        let mut constraints = Vec::<expr::Expr>::new();
+        let mut is_open = None;
         for constraint_str in args.1 {
-            amp_parser.parse(&format!("&{constraint_str}"), &amp::parse::Match::OnlyStart)?;
-            if let Some(stmt) = amp_parser.stmts.first() {
-                match &stmt.kind {
-                    amp::parse::Kind::Amp(kv) => constraints.push(kv.clone()),
-                    _ => fail!("Expected to find AMP"),
+            // &doc: '~open' and '~closed' filter on the semantics of the Status
+            match constraint_str.as_str() {
+                "~open" => {
+                    is_open = Some(true);
+                    continue;
+                }
+                "~closed" => {
+                    is_open = Some(false);
+                    continue;
                 }
+                _ => {}
             }
+            constraints.push(expr::Expr::try_from(constraint_str.as_str())?);
         }
         trace!("constraints: {:?}", constraints);
 
         Ok(Query {
             needle,
             constraints,
+            is_open,
+            ..Default::default()
         })
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use crate::tree::{builder, test_util};
+
     #[test]
     fn test_api() {}
+
+    #[test]
+    fn test_recommended_order() -> util::Result<()> {
+        let mut builder = builder::Builder::new();
+        let forest = builder.create_forest_from_md(&[
+            "# Plan &!:status:~status\n- &!:due:~date\n- &!:scope:~scope\n- &!:prio:~priority\n\
+            - A &todo &must\n\
+            - B &todo &due:2024-01-01\n\
+            - C &todo &due:2023-01-01\n\
+            - D &todo &must &order=1\n\
+            - E &!:e &todo &could\n\
+            - F &todo &must &e&\n\
+            - G &done &must\n\
+            - H &todo &could &prio:a1",
+        ])?;
+
+        let query = Query {
+            schedule: true,
+            is_open: Some(true),
+            ..Default::default()
+        };
+        let mut answer = search(&forest, &query, &From::Org)?;
+        answer.order(&answer::By::Rank);
+
+        let mut names = String::new();
+        answer.each_location(|location, _meta| {
+            names.push_str(test_util::name(&location.content).unwrap_or("?"));
+        });
+        // Within a layer: manual order, prio and Rank, so `must` A comes before the due dates of C and B
+        assert_eq!(names, "DHACBEF");
+
+        // A dependency cycle is reported instead of an unreliable order
+        let forest = builder.create_forest_from_md(&[
+            "# Plan &!:status:~status\n- X &!:x &y& &todo\n- Y &!:y &x& &todo",
+        ])?;
+        assert!(search(&forest, &query, &From::Org).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reference_date() -> util::Result<()> {
+        let env = amp::Env {
+            today: amp::Date::new(2024, 10, 10),
+            ..Default::default()
+        };
+        let mut builder = builder::Builder::new().with_env(env);
+        let forest =
+            builder.create_forest_from_md(&["# Plan &!:status:~status\n- &!:due:~date\n\
+            - A &todo &due:2024-10-07\n\
+            - B &todo &due:tomorrow\n\
+            - C &done &due:2024-10-01\n\
+            - D &todo &due:-1d"])?;
+
+        let query = Query {
+            needle: Some(expr::Expr::try_from("due<today")?),
+            ..Default::default()
+        };
+        let mut answer = search(&forest, &query, &From::Org)?;
+        answer.order(&answer::By::Name);
+        let mut overdues = Vec::new();
+        answer.each_location(|location, _meta| {
+            overdues.push((location.content.clone(), location.overdue));
+        });
+        assert_eq!(
+            overdues,
+            [
+                ("- A &todo &due:2024-10-07".to_owned(), Some(3)),
+                ("- C &done &due:2024-10-01".to_owned(), None),
+                ("- D &todo &due:-1d".to_owned(), Some(1)),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_suggest() -> util::Result<()> {
+        let mut builder = builder::Builder::new();
+        let forest = builder.create_forest_from_md(&[
+            "# Plan &!:status:~status\n- &!:feature\n- &!:fixture\n- &!:team:owner\n- &!:bug",
+        ])?;
+
+        let scns = [
+            ("feture", vec![":feature", ":fixture"]),
+            ("taem", vec![":team:owner"]),
+            ("ownr=alice", vec![":team:owner"]),
+            ("bgu", vec![":bug"]),
+            ("bgu or xyz", vec![":bug"]),
+            ("qqq", vec![]),
+        ];
+        for (needle, exp) in scns {
+            let mut query = Query::try_from((&Some(needle.to_string()), &vec![]))?;
+            let suggestions = suggest(&forest, query.needle.as_ref().unwrap(), 5)
+                .iter()
+                .map(|path| path.to_string())
+                .collect::<Vec<_>>();
+            assert_eq!(suggestions, exp, "{needle}");
+
+            query.make_fuzzy();
+            assert!(matches!(query.needle, Some(expr::Expr::Fuzzy(_))));
+        }
+
+        Ok(())
+    }
 }
@@ -0,0 +1,617 @@
+use crate::{amp, fail, query::From, tree, util};
+
+type Ixs = std::collections::BTreeSet<tree::NodeIx>;
+
+// Boolean expression over AMP Paths, eg `(bug or feature) and not done`
+// - `not` binds stronger than `and`, which binds stronger than `or`. Adjacent terms are combined with `and`.
+// - `org(...)`, `ctx(...)` and `def(...)` select where the enclosed Paths are searched
+// - `~open` and `~closed` filter on the semantics of the Status
+// - `due<today`, `prio<=B3`, `duration>1d` or `status!=done` compare typed Parts, see Compare
+// - `fuzzy(...)` lets the enclosed Tags match with Tags that are a few edits away
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Path(amp::Path),
+    Compare(Compare),
+    IsOpen(bool),
+    Not(Box<Expr>),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Scope(From, Box<Expr>),
+    Fuzzy(Box<Expr>),
+}
+
+impl Expr {
+    // `from` determines where Paths outside of an explicit scope are searched
+    pub fn matches(&self, node: &tree::Node, from: &From, env: &amp::Env) -> bool {
+        self.matches_(node, from, false, env)
+    }
+
+    fn matches_(&self, node: &tree::Node, from: &From, fuzzy: bool, env: &amp::Env) -> bool {
+        match self {
+            Expr::Path(path) => {
+                let matches = |paths: &amp::Paths| {
+                    if fuzzy {
+                        paths.fuzzy_matches_with(path, env)
+                    } else {
+                        paths.matches_with(path, env)
+                    }
+                };
+                match from {
+                    From::Org => matches(&node.org),
+                    From::Ctx => matches(&node.ctx),
+                    From::Def => node.def.as_ref().is_some_and(|def| {
+                        if fuzzy {
+                            def.fuzzy_matches_with(path, env)
+                        } else {
+                            def.matches_with(path, false, env)
+                        }
+                    }),
+                }
+            }
+            Expr::Compare(compare) => match from {
+                From::Org => compare.matches_paths(&node.org.data, env),
+                From::Ctx => compare.matches_paths(&node.ctx.data, env),
+                From::Def => node
+                    .def
+                    .as_ref()
+                    .is_some_and(|def| compare.matches_paths(std::slice::from_ref(def), env)),
+            },
+            Expr::IsOpen(is_open) => node.is_open() == *is_open,
+            Expr::Not(expr) => !expr.matches_(node, from, fuzzy, env),
+            Expr::And(exprs) => exprs
+                .iter()
+                .all(|expr| expr.matches_(node, from, fuzzy, env)),
+            Expr::Or(exprs) => exprs
+                .iter()
+                .any(|expr| expr.matches_(node, from, fuzzy, env)),
+            Expr::Scope(from, expr) => expr.matches_(node, from, fuzzy, env),
+            Expr::Fuzzy(expr) => expr.matches_(node, from, true, env),
+        }
+    }
+
+    // Candidate Nodes from `index` that might match, None when the index cannot answer this
+    pub fn candidates(&self, index: &tree::index::Index, from: &From) -> Option<Ixs> {
+        let scope = match from {
+            From::Org => tree::index::Scope::Org,
+            From::Ctx => tree::index::Scope::Ctx,
+            From::Def => return None,
+        };
+        match self {
+            Expr::Path(path) => index.lookup(scope, path),
+            Expr::Compare(compare) => compare.candidates(index, scope),
+            Expr::IsOpen(_) | Expr::Not(_) | Expr::Fuzzy(_) => None,
+            // Each operand restricts the candidates, operands that cannot be answered are skipped
+            Expr::And(exprs) => exprs
+                .iter()
+                .filter_map(|expr| expr.candidates(index, from))
+                .reduce(|lhs, rhs| lhs.intersection(&rhs).copied().collect()),
+            Expr::Or(exprs) => {
+                let mut ixs = Ixs::new();
+                for expr in exprs {
+                    ixs.extend(expr.candidates(index, from)?);
+                }
+                Some(ixs)
+            }
+            Expr::Scope(from, expr) => expr.candidates(index, from),
+        }
+    }
+
+    // All Paths that occur in this Expr
+    pub fn paths(&self) -> Vec<&amp::Path> {
+        match self {
+            Expr::Path(path) => vec![path],
+            Expr::Compare(_) | Expr::IsOpen(_) => Vec::new(),
+            Expr::Not(expr) | Expr::Scope(_, expr) | Expr::Fuzzy(expr) => expr.paths(),
+            Expr::And(exprs) | Expr::Or(exprs) => exprs.iter().flat_map(Expr::paths).collect(),
+        }
+    }
+}
+
+impl TryFrom<&str> for Expr {
+    type Error = util::ErrorType;
+    fn try_from(s: &str) -> util::Result<Expr> {
+        let mut parser = Parser {
+            tokens: tokenize(s),
+            ix: 0,
+            amp_parser: amp::parse::Parser::new(),
+        };
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.peek() {
+            fail!("Unexpected '{}' in query '{}'", token, s);
+        }
+        Ok(expr)
+    }
+}
+
+impl std::fmt::Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut join = |exprs: &Vec<Expr>, sep: &str| -> std::fmt::Result {
+            write!(f, "(")?;
+            for (ix, expr) in exprs.iter().enumerate() {
+                if ix > 0 {
+                    write!(f, " {sep} ")?;
+                }
+                write!(f, "{expr}")?;
+            }
+            write!(f, ")")
+        };
+        match self {
+            Expr::Path(path) => write!(f, "{path}"),
+            Expr::Compare(compare) => write!(f, "{compare}"),
+            Expr::IsOpen(true) => write!(f, "~open"),
+            Expr::IsOpen(false) => write!(f, "~closed"),
+            Expr::Not(expr) => write!(f, "not {expr}"),
+            Expr::And(exprs) => join(exprs, "and"),
+            Expr::Or(exprs) => join(exprs, "or"),
+            Expr::Scope(from, expr) => {
+                let name = match from {
+                    From::Org => "org",
+                    From::Ctx => "ctx",
+                    From::Def => "def",
+                };
+                write!(f, "{name}({expr})")
+            }
+            Expr::Fuzzy(expr) => write!(f, "fuzzy({expr})"),
+        }
+    }
+}
+
+// Compares the typed Parts selected by `key` with `value`, eg `due<today`. A typed Part is selected when
+// - it directly follows Tag `key`, eg `:due:2024-10-01` for `due`
+// - `key` names its type (date, duration, priority, status, scope, risk or effort), eg `duration` for `:est:2d`
+// Nodes without such a Part never match, also not for `!=`. Status only supports `!=`.
+// Relative Dates like `today` are interpreted when matching, against Env.today.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Compare {
+    pub key: String,
+    pub op: Op,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Ne,
+}
+
+// `Compare.value` interpreted as each of the typed Parts, when possible
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct Typed {
+    date: Option<amp::Date>,
+    duration: Option<amp::Duration>,
+    prio: Option<amp::Prio>,
+    scope: Option<amp::Scope>,
+    risk: Option<amp::Risk>,
+    effort: Option<amp::Effort>,
+    number: Option<i64>,
+}
+
+impl Compare {
+    // Operators are checked in order, `<=` must be found before `<`
+    const OPS: [(&'static str, Op); 5] = [
+        ("<=", Op::Le),
+        (">=", Op::Ge),
+        ("!=", Op::Ne),
+        ("<", Op::Lt),
+        (">", Op::Gt),
+    ];
+
+    // Returns None when `s` contains no comparison operator
+    pub fn parse(s: &str) -> util::Result<Option<Compare>> {
+        let Some((ix, (op_str, op))) = Self::OPS
+            .iter()
+            .filter_map(|op| s.find(op.0).map(|ix| (ix, op)))
+            .min_by_key(|(ix, _)| *ix)
+        else {
+            return Ok(None);
+        };
+        let key = &s[..ix];
+        let value = &s[ix + op_str.len()..];
+        if key.is_empty() || value.is_empty() {
+            fail!("Expected 'KEY{}VALUE', got '{}'", op_str, s);
+        }
+
+        let typed = Typed::new(value, &amp::Env::default());
+        if typed == Typed::default() && *op != Op::Ne {
+            fail!("Could not interpret '{}' as a value to compare with", value);
+        }
+
+        Ok(Some(Compare {
+            key: key.into(),
+            op: *op,
+            value: value.into(),
+        }))
+    }
+
+    pub fn matches_paths(&self, paths: &[amp::Path], env: &amp::Env) -> bool {
+        let typed = Typed::new(&self.value, env);
+        paths.iter().any(|path| {
+            path.parts.iter().enumerate().any(|(ix, part)| {
+                let follows_key = ix > 0
+                    && matches!(&path.parts[ix - 1], amp::Part::Tag(tag) if amp::is_same_text(&tag.text, &self.key));
+                let has_key = follows_key || self.is_type_key(part);
+                self.matches_part(part, has_key, &typed)
+            })
+        })
+    }
+
+    // Candidate Nodes from `index`, None when `key` names a type since typed Parts are not indexed
+    fn candidates(&self, index: &tree::index::Index, scope: tree::index::Scope) -> Option<Ixs> {
+        let is_type_key = Self::TYPE_KEYS
+            .iter()
+            .any(|name| amp::is_same_text(name, &self.key));
+        (!is_type_key).then(|| index.lookup_text(scope, &self.key))
+    }
+
+    const TYPE_KEYS: [&'static str; 7] = [
+        "date", "duration", "priority", "status", "scope", "risk", "effort",
+    ];
+
+    fn is_type_key(&self, part: &amp::Part) -> bool {
+        part.template_name()
+            .and_then(|name| name.strip_prefix('~'))
+            .is_some_and(|name| amp::is_same_text(name, &self.key))
+    }
+
+    fn matches_part(&self, part: &amp::Part, has_key: bool, typed: &Typed) -> bool {
+        use amp::Part;
+        let ordering = match part {
+            Part::Date(v) if has_key => typed.date.as_ref().map(|rhs| v.cmp(rhs)),
+            Part::Duration(v) if has_key => typed.duration.as_ref().map(|rhs| v.cmp(rhs)),
+            Part::Estimate(v) if has_key => {
+                typed.duration.as_ref().map(|rhs| v.expected().cmp(rhs))
+            }
+            Part::Prio(v) if has_key => typed.prio.as_ref().map(|rhs| v.cmp(rhs)),
+            Part::Scope(v) if has_key => typed.scope.as_ref().map(|rhs| v.cmp(rhs)),
+            Part::Risk(v) if has_key => typed.risk.as_ref().map(|rhs| v.cmp(rhs)),
+            Part::Effort(v) if has_key => typed.effort.as_ref().map(|rhs| v.cmp(rhs)),
+            Part::Status(v) if has_key => {
+                return self.op == Op::Ne && !amp::is_same_text(&v.name, &self.value);
+            }
+            // Attributes like `&order=3` are compared numerically
+            Part::KeyValue(amp::KeyValue(key, Some(v))) if amp::is_same_text(key, &self.key) => {
+                match (v.parse::<i64>().ok(), typed.number) {
+                    (Some(lhs), Some(rhs)) => Some(lhs.cmp(&rhs)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        ordering.is_some_and(|ordering| match self.op {
+            Op::Lt => ordering.is_lt(),
+            Op::Le => ordering.is_le(),
+            Op::Gt => ordering.is_gt(),
+            Op::Ge => ordering.is_ge(),
+            Op::Ne => ordering.is_ne(),
+        })
+    }
+}
+
+impl Typed {
+    fn new(value: &str, env: &amp::Env) -> Typed {
+        Typed {
+            date: amp::Date::parse(value, &env.today).ok(),
+            duration: amp::Duration::parse(value, &env.calendar).ok(),
+            prio: amp::Prio::try_from(value).ok(),
+            scope: amp::Scope::try_from(value).ok(),
+            risk: amp::Risk::try_from(value).ok(),
+            effort: amp::Effort::try_from(value).ok(),
+            number: value.parse().ok(),
+        }
+    }
+}
+
+impl std::fmt::Display for Compare {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = Self::OPS
+            .iter()
+            .find_map(|(s, op)| (*op == self.op).then_some(*s))
+            .unwrap_or("?");
+        write!(f, "{}{}{}", &self.key, op, &self.value)
+    }
+}
+
+// `(` and `)` are Tokens on their own, other Tokens are separated by whitespace
+fn tokenize(s: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    for ch in s.chars() {
+        if ch.is_whitespace() || ch == '(' || ch == ')' {
+            if !word.is_empty() {
+                tokens.push(std::mem::take(&mut word));
+            }
+            if !ch.is_whitespace() {
+                tokens.push(ch.to_string());
+            }
+        } else {
+            word.push(ch);
+        }
+    }
+    if !word.is_empty() {
+        tokens.push(word);
+    }
+    tokens
+}
+
+struct Parser {
+    tokens: Vec<String>,
+    ix: usize,
+    amp_parser: amp::parse::Parser,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.ix).map(String::as_str)
+    }
+    fn is_keyword(&self, keyword: &str) -> bool {
+        self.peek()
+            .is_some_and(|token| token.eq_ignore_ascii_case(keyword))
+    }
+
+    fn parse_or(&mut self) -> util::Result<Expr> {
+        let mut exprs = vec![self.parse_and()?];
+        while self.is_keyword("or") {
+            self.ix += 1;
+            exprs.push(self.parse_and()?);
+        }
+        Ok(simplify(exprs, Expr::Or))
+    }
+
+    fn parse_and(&mut self) -> util::Result<Expr> {
+        let mut exprs = vec![self.parse_not()?];
+        loop {
+            if self.is_keyword("and") {
+                self.ix += 1;
+            } else if self.peek().is_none() || self.peek() == Some(")") || self.is_keyword("or") {
+                break;
+            }
+            exprs.push(self.parse_not()?);
+        }
+        Ok(simplify(exprs, Expr::And))
+    }
+
+    fn parse_not(&mut self) -> util::Result<Expr> {
+        if self.is_keyword("not") {
+            self.ix += 1;
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_term()
+    }
+
+    fn parse_term(&mut self) -> util::Result<Expr> {
+        let Some(token) = self.peek().map(str::to_owned) else {
+            fail!("Expected a query term, found the end of the query");
+        };
+        self.ix += 1;
+
+        let scope = match token.to_ascii_lowercase().as_str() {
+            "org" => Some(From::Org),
+            "ctx" => Some(From::Ctx),
+            "def" => Some(From::Def),
+            _ => None,
+        };
+        if let Some(scope) = scope.filter(|_| self.peek() == Some("(")) {
+            self.ix += 1;
+            let expr = self.parse_group()?;
+            return Ok(Expr::Scope(scope, Box::new(expr)));
+        }
+        if token.eq_ignore_ascii_case("fuzzy") && self.peek() == Some("(") {
+            self.ix += 1;
+            let expr = self.parse_group()?;
+            return Ok(Expr::Fuzzy(Box::new(expr)));
+        }
+
+        match token.as_str() {
+            "(" => self.parse_group(),
+            ")" => fail!("Unexpected ')', expected a query term"),
+            "~open" => Ok(Expr::IsOpen(true)),
+            "~closed" => Ok(Expr::IsOpen(false)),
+            _ => {
+                if let Some(compare) = Compare::parse(&token)? {
+                    return Ok(Expr::Compare(compare));
+                }
+                self.amp_parser
+                    .parse(&format!("&{token}"), &amp::parse::Match::OnlyStart)?;
+                match self.amp_parser.stmts.first().map(|stmt| &stmt.kind) {
+                    Some(amp::parse::Kind::Amp(path)) => Ok(Expr::Path(path.clone())),
+                    _ => fail!("Expected to find AMP in '{}'", token),
+                }
+            }
+        }
+    }
+
+    // Parses the remainder of a group, after its opening `(`
+    fn parse_group(&mut self) -> util::Result<Expr> {
+        let expr = self.parse_or()?;
+        if self.peek() != Some(")") {
+            fail!("Expected ')' to close the group");
+        }
+        self.ix += 1;
+        Ok(expr)
+    }
+}
+
+fn simplify(mut exprs: Vec<Expr>, create: impl Fn(Vec<Expr>) -> Expr) -> Expr {
+    if exprs.len() == 1 {
+        exprs.remove(0)
+    } else {
+        create(exprs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::{builder, test_util};
+
+    #[test]
+    fn test_expr_try_from() {
+        let scns = [
+            ("todo", Some("todo")),
+            ("a b", Some("(a and b)")),
+            ("a or b and c", Some("(a or (b and c))")),
+            ("(a or b) and c", Some("((a or b) and c)")),
+            (
+                "(bug OR feature) and not done",
+                Some("((bug or feature) and not done)"),
+            ),
+            ("not not a", Some("not not a")),
+            ("org(a) ctx(b or c)", Some("(org(a) and ctx((b or c)))")),
+            ("org or a", Some("(org or a)")),
+            ("~open and not ~closed", Some("(~open and not ~closed)")),
+            ("!:proj:a", Some("!:proj:a")),
+            ("fuzzy(a or b) c", Some("(fuzzy((a or b)) and c)")),
+            ("due<today", Some("due<today")),
+            (
+                "prio<=B3 and not status!=done",
+                Some("(prio<=B3 and not status!=done)"),
+            ),
+            ("due<", None),
+            ("<1d", None),
+            ("duration>xyz", None),
+            ("", None),
+            ("a and", None),
+            ("(a or b", None),
+            ("a)", None),
+            ("not", None),
+        ];
+
+        for (s, exp) in scns {
+            let expr = Expr::try_from(s).ok().map(|expr| expr.to_string());
+            assert_eq!(expr.as_deref(), exp, "{s}");
+        }
+    }
+
+    #[test]
+    fn test_expr_matches() -> util::Result<()> {
+        let mut builder = builder::Builder::new();
+        let forest = builder.create_forest_from_md(&[
+            "# Plan &!:status:~status\n- &!:bug\n- &!:feature\n\
+            ## Parser &bug\n\
+            - A &todo\n\
+            - B &done\n\
+            ## Lexer &feature\n\
+            - C &todo &bug",
+        ])?;
+
+        let scns = [
+            ("bug", From::Ctx, "ABC"),
+            ("bug", From::Org, "C"),
+            ("(bug or feature) and not done", From::Ctx, "AC"),
+            ("ctx(feature) and org(bug)", From::Org, "C"),
+            ("bug ~closed", From::Ctx, "B"),
+            ("not bug", From::Ctx, ""),
+            ("bgu", From::Ctx, ""),
+            ("fuzzy(bgu)", From::Ctx, "ABC"),
+            ("fuzzy(featrue) and fuzzy(tood)", From::Ctx, "C"),
+            ("fuzzy(ctx(featrue) and not dnoe)", From::Org, "C"),
+        ];
+
+        for (s, from, exp) in scns {
+            assert_eq!(names(&forest, &Expr::try_from(s)?, &from)?, exp, "{s}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compare() -> util::Result<()> {
+        let mut builder = builder::Builder::new();
+        let forest = builder.create_forest_from_md(&[
+            "# Plan &!:status:~status\n- &!:due:~date\n- &!:prio:~priority\n\
+            - &!:est:~duration\n- &!:effort:~effort\n\
+            - A &todo &due:2024-10-01 &prio:a2 &est:1d\n\
+            - B &done &due:2024-11-01 &prio:B3 &est:1h..3h\n\
+            - C &wip &prio:c0 &effort:xl &order=2\n\
+            - D &order=5",
+        ])?;
+
+        let scns = [
+            ("due<2024-10-15", "A"),
+            ("due>=2024-10-01", "AB"),
+            ("due!=2024-10-01", "B"),
+            ("prio<=B3", "AB"),
+            ("prio>B3", "C"),
+            ("est>1d", ""),
+            ("est>=2h", "AB"),
+            ("est<4h", "B"),
+            ("status!=done", "AC"),
+            ("effort>=l", "C"),
+            ("order<3", "C"),
+            ("order>=2 and status!=done", "C"),
+            ("due<2024-12-01 or order>3", "ABD"),
+        ];
+        for (s, exp) in scns {
+            assert_eq!(names(&forest, &Expr::try_from(s)?, &From::Ctx)?, exp, "{s}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compare_search() -> util::Result<()> {
+        let env = amp::Env {
+            today: amp::Date::new(2024, 10, 10),
+            ..Default::default()
+        };
+        let mut builder = builder::Builder::new().with_env(env);
+        let plan = builder.create_forest_from_md(&[
+            "# Plan &!:status:~status\n- &!:due:~date\n- &!:deadline:~date\n\
+            - &!:prio:~priority\n- &!:est:~duration\n\
+            - A &todo &due:2024-10-01 &prio:a2 &est:1d\n\
+            - B &done &due:2024-11-01 &prio:B3 &est:1h..3h\n\
+            - C &wip &deadline:2024-09-01 &est:2d\n\
+            - [ ] D &due:tomorrow\n\
+            - [x] E &est:1w",
+        ])?;
+        // Checkbox Statuses without a `~status` def do not follow a `status` Tag
+        let checklist = builder.create_forest_from_md(&["- [ ] F fix\n- [x] G go"])?;
+
+        let scns = [
+            (&plan, "due<today", "A"),
+            (&plan, "deadline<today", "C"),
+            (&plan, "date<today", "AC"),
+            (&plan, "prio<=B3", "AB"),
+            (&plan, "priority>B3", ""),
+            (&plan, "duration>1d", "CE"),
+            (&plan, "est>1d", "CE"),
+            (&plan, "status!=done", "ACD"),
+            (&checklist, "status!=done", "F"),
+        ];
+        for (forest, s, exp) in scns {
+            let expr = Expr::try_from(s)?;
+            let query = crate::query::Query {
+                needle: Some(expr.clone()),
+                ..Default::default()
+            };
+            // The search uses the index, names() checks each Node
+            let mut answer = crate::query::search(forest, &query, &From::Org)?;
+            answer.order(&crate::answer::By::Name);
+            let mut found = String::new();
+            answer.each_location(|location, _meta| {
+                found.push_str(test_util::name(&location.content).unwrap_or("?"));
+            });
+            assert_eq!(found, exp, "{s}");
+            assert_eq!(names(forest, &expr, &From::Org)?, exp, "{s}");
+        }
+
+        Ok(())
+    }
+
+    // First letters of the items `- X ...` or `- [ ] X ...` that match `expr`
+    fn names(forest: &tree::Forest, expr: &Expr, from: &From) -> util::Result<String> {
+        let mut names = String::new();
+        forest.dfs(|tree, node| {
+            if let Some(name) = test_util::name(&node.content(tree)) {
+                if expr.matches(node, from, &forest.env) {
+                    names.push_str(name);
+                }
+            }
+            Ok(())
+        })?;
+        Ok(names)
+    }
+}
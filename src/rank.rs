@@ -0,0 +1,109 @@
+use crate::amp;
+
+// Recommended execution order, computed from scope, priority, risk and effort, see doc/priority.md
+// A higher score should be executed earlier
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Rank {
+    pub score: u32,
+}
+
+// Values that are not specified are considered average
+#[derive(Debug, Default)]
+pub struct Input<'a> {
+    pub scope: Option<&'a amp::Scope>,
+    pub prio: Option<&'a amp::Prio>,
+    pub risk: Option<&'a amp::Risk>,
+    pub effort: Option<&'a amp::Effort>,
+}
+
+impl Rank {
+    // rank_score = scope + priority + risk + effort_bonus
+    pub fn new(input: &Input) -> Rank {
+        let scope = match input.scope.unwrap_or(&amp::Scope::Should) {
+            amp::Scope::Must => 300,
+            amp::Scope::Should => 200,
+            amp::Scope::Could => 100,
+            amp::Scope::Deferred => 0,
+        };
+        // Both 'A' and 'a' are highest
+        let prio = match input.prio.map(|prio| prio.major / 2).unwrap_or(2) {
+            0 => 40,
+            1 => 30,
+            2 => 20,
+            _ => 10,
+        };
+        // Risky items go earlier to reduce uncertainty
+        let risk = match input.risk.unwrap_or(&amp::Risk::Medium) {
+            amp::Risk::High => 30,
+            amp::Risk::Medium => 20,
+            amp::Risk::Low => 10,
+        };
+        // Smaller items go earlier
+        let effort = match input.effort.unwrap_or(&amp::Effort::M) {
+            amp::Effort::XS => 20,
+            amp::Effort::S => 15,
+            amp::Effort::M => 10,
+            amp::Effort::L => 5,
+            amp::Effort::XL => 0,
+        };
+
+        Rank {
+            score: scope + prio + risk + effort,
+        }
+    }
+
+    // Uses the first value found for each of the inputs
+    pub fn from_paths(paths: &amp::Paths) -> Rank {
+        let mut input = Input::default();
+        for path in &paths.data {
+            input.scope = input.scope.or(path.get_scope());
+            input.prio = input.prio.or(path.get_prio());
+            input.risk = input.risk.or(path.get_risk());
+            input.effort = input.effort.or(path.get_effort());
+        }
+        Rank::new(&input)
+    }
+}
+
+impl std::fmt::Display for Rank {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.score)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank() -> crate::util::Result<()> {
+        let scns = [
+            ("", 250),
+            ("must", 350),
+            ("must A0 high xs", 390),
+            ("could c0 low xl", 130),
+            ("deferred z9 low xl", 20),
+        ];
+        for (s, exp) in scns {
+            let mut input = Input::default();
+            let mut scope = None;
+            let mut prio = None;
+            let mut risk = None;
+            let mut effort = None;
+            for (ix, word) in s.split_whitespace().enumerate() {
+                match ix {
+                    0 => scope = Some(amp::Scope::try_from(word)?),
+                    1 => prio = Some(amp::Prio::try_from(word)?),
+                    2 => risk = Some(amp::Risk::try_from(word)?),
+                    _ => effort = Some(amp::Effort::try_from(word)?),
+                }
+            }
+            input.scope = scope.as_ref();
+            input.prio = prio.as_ref();
+            input.risk = risk.as_ref();
+            input.effort = effort.as_ref();
+            assert_eq!(Rank::new(&input).score, exp, "{s}");
+        }
+        Ok(())
+    }
+}
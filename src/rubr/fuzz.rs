@@ -0,0 +1,115 @@
+// Fuzzy distance from `needle` to `haystack`, lower is better
+// - Only if `needle` contains upper-case letters, case-sensitive search will happen
+// - Each needle char contributes log2 of the distance to its next occurrence in `haystack`
+// Returns the average distance per needle char and the number of chars that could not be matched
+pub fn distance(needle: &str, haystack: &str) -> (f64, usize) {
+    let case_sensitive = needle.chars().any(char::is_uppercase);
+    let normalize = |ch: char| {
+        if case_sensitive {
+            ch
+        } else {
+            ch.to_ascii_lowercase()
+        }
+    };
+    let needle = needle.chars().map(normalize).collect::<Vec<_>>();
+    let haystack = haystack.chars().map(normalize).collect::<Vec<_>>();
+
+    let index_of = |slice: &[char], ch: char| slice.iter().position(|&c| c == ch);
+
+    let mut sum = 0.0;
+    let mut skip_count = 0;
+    let mut offset = 0;
+    for &ch in &needle {
+        if offset >= haystack.len() {
+            offset = 0;
+        }
+        let d = if let Some(ix) = index_of(&haystack[offset..], ch) {
+            offset += ix + 1;
+            ix
+        } else if let Some(ix) = index_of(&haystack[0..offset], ch) {
+            let d = haystack.len() - offset + ix;
+            offset = ix + 1;
+            d
+        } else {
+            skip_count += 1;
+            haystack.len()
+        };
+        sum += ((d + 1) as f64).log2();
+    }
+
+    if needle.is_empty() {
+        (0.0, skip_count)
+    } else {
+        (sum / needle.len() as f64, skip_count)
+    }
+}
+
+// Optimal string alignment distance: the minimal number of char insertions, deletions,
+// substitutions and transpositions of adjacent chars
+pub fn edit_distance(lhs: &str, rhs: &str) -> usize {
+    let lhs = lhs.chars().collect::<Vec<_>>();
+    let rhs = rhs.chars().collect::<Vec<_>>();
+
+    // d[i][j] is the distance between the first i chars of lhs and the first j chars of rhs
+    let mut d = vec![vec![0; rhs.len() + 1]; lhs.len() + 1];
+    d[0] = (0..=rhs.len()).collect();
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for i in 1..=lhs.len() {
+        for j in 1..=rhs.len() {
+            let cost = usize::from(lhs[i - 1] != rhs[j - 1]);
+            d[i][j] = (d[i - 1][j - 1] + cost)
+                .min(d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1);
+            if i > 1 && j > 1 && lhs[i - 1] == rhs[j - 2] && lhs[i - 2] == rhs[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[lhs.len()][rhs.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distance() {
+        let scns = [
+            ("abc", "abc", 0.0, 0),
+            ("", "abc", 0.0, 0),
+            ("ac", "abc", 0.5, 0),
+            ("ABC", "abc", 2.0, 3),
+            ("abc", "ABC", 0.0, 0),
+            ("x", "abc", 2.0, 1),
+        ];
+        for (needle, haystack, exp_distance, exp_skip_count) in scns {
+            let (distance, skip_count) = distance(needle, haystack);
+            assert_eq!(distance, exp_distance, "{needle} {haystack}");
+            assert_eq!(skip_count, exp_skip_count, "{needle} {haystack}");
+        }
+
+        // Closer matches have a smaller distance
+        assert!(distance("tod", "todo").0 < distance("tod", "t_o_d_o").0);
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        let scns = [
+            ("", "", 0),
+            ("abc", "", 3),
+            ("", "abc", 3),
+            ("todo", "todo", 0),
+            ("tood", "todo", 1),
+            ("taem", "team", 1),
+            ("ca", "abc", 3),
+            ("feature", "feture", 1),
+            ("kitten", "sitting", 3),
+        ];
+        for (lhs, rhs, exp) in scns {
+            assert_eq!(edit_distance(lhs, rhs), exp, "{lhs} {rhs}");
+            assert_eq!(edit_distance(rhs, lhs), exp, "{rhs} {lhs}");
+        }
+    }
+}
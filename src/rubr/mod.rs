@@ -1,3 +1,4 @@
 pub mod counter;
+pub mod fuzz;
 pub mod naft;
 pub mod strange;
@@ -0,0 +1,151 @@
+use crate::tree;
+use crate::{fail, util};
+use std::collections;
+
+// Layers of the prerequisite graph between open Nodes, used for the recommended order (doc/priority.md)
+// - Layer 0 contains the Nodes that can be started now
+// - Layer n contains the Nodes that depend on open Nodes from layer n-1 or lower
+// Closed prerequisites are ignored. Cycles are collected in Schedule.cycles and broken at the first Node that is revisited.
+#[derive(Default, Debug)]
+pub struct Schedule {
+    layers: collections::BTreeMap<tree::NodeIx, usize>,
+    pub cycles: Vec<Vec<tree::NodeIx>>,
+}
+
+impl Schedule {
+    pub fn new(forest: &tree::Forest) -> Schedule {
+        let mut schedule = Schedule::default();
+
+        let mut stack = Vec::new();
+        for (tree_ix, tree) in forest.trees.iter().enumerate() {
+            for node_ix in 0..tree.nodes.len() {
+                schedule.visit(forest, tree::NodeIx { tree_ix, node_ix }, &mut stack);
+            }
+        }
+
+        schedule
+    }
+
+    // Fails with each cycle as `a.md:1 -> a.md:2 -> a.md:1`, layers are unreliable in this case
+    pub fn check_cycles(&self, forest: &tree::Forest) -> util::Result<()> {
+        if self.cycles.is_empty() {
+            return Ok(());
+        }
+        let cycles = self
+            .cycles
+            .iter()
+            .map(|cycle| {
+                cycle
+                    .iter()
+                    .chain(cycle.first())
+                    .map(|ix| forest.location(ix))
+                    .collect::<Vec<_>>()
+                    .join(" -> ")
+            })
+            .collect::<Vec<_>>();
+        fail!("Found dependency cycles:\n{}", cycles.join("\n"));
+    }
+
+    // Nodes that are closed or not part of the Forest are in layer 0
+    pub fn layer(&self, ix: &tree::NodeIx) -> usize {
+        self.layers.get(ix).copied().unwrap_or(0)
+    }
+
+    // Returns the layer for `ix`, or None when `ix` is still on the `stack`
+    fn visit(
+        &mut self,
+        forest: &tree::Forest,
+        ix: tree::NodeIx,
+        stack: &mut Vec<tree::NodeIx>,
+    ) -> Option<usize> {
+        if let Some(layer) = self.layers.get(&ix) {
+            return Some(*layer);
+        }
+        if let Some(pos) = stack.iter().position(|other| other == &ix) {
+            self.cycles.push(stack[pos..].to_vec());
+            return None;
+        }
+
+        let mut layer = 0;
+        if let Some(node) = forest.node(&ix) {
+            if node.is_open() {
+                stack.push(ix);
+                for prereq_ix in &node.prereqs {
+                    let is_open = forest
+                        .node(prereq_ix)
+                        .is_some_and(|prereq| prereq.is_open());
+                    if is_open {
+                        if let Some(prereq_layer) = self.visit(forest, *prereq_ix, stack) {
+                            layer = layer.max(prereq_layer + 1);
+                        }
+                    }
+                }
+                stack.pop();
+            }
+        }
+
+        self.layers.insert(ix, layer);
+        Some(layer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        tree::{builder, test_util},
+        util,
+    };
+
+    fn layers(forest: &tree::Forest, schedule: &Schedule) -> Vec<(String, usize)> {
+        let mut layers = Vec::new();
+        forest
+            .each_node(|tree, node| {
+                if let Some(name) = test_util::name(&node.content(tree)) {
+                    layers.push((name.to_string(), schedule.layer(&node.ix())));
+                }
+                Ok(())
+            })
+            .unwrap();
+        layers
+    }
+
+    #[test]
+    fn test_layers() -> util::Result<()> {
+        let mut builder = builder::Builder::new();
+        let forest = builder.create_forest_from_md(&[
+            "# Status &!:status:~status\n- A &!:a &todo\n- B &!:b &a& &todo\n- C &b& &a& &todo\n- D &todo\n- E &!:e &done\n- F &e& &todo",
+        ])?;
+        let schedule = Schedule::new(&forest);
+
+        let exp = [("A", 0), ("B", 1), ("C", 2), ("D", 0), ("E", 0), ("F", 0)];
+        let exp = exp
+            .iter()
+            .map(|(name, layer)| (name.to_string(), *layer))
+            .collect::<Vec<_>>();
+        assert_eq!(layers(&forest, &schedule), exp);
+        assert!(schedule.cycles.is_empty());
+        schedule.check_cycles(&forest)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cycles() -> util::Result<()> {
+        let mut builder = builder::Builder::new();
+        let forest = builder.create_forest_from_md(&[
+            "# Status &!:status:~status\n- X &!:x &z& &todo\n- Y &!:y &x& &todo\n- Z &!:z &y& &todo\n- W &x& &todo",
+        ])?;
+        let schedule = Schedule::new(&forest);
+
+        assert_eq!(schedule.cycles.len(), 1);
+        assert_eq!(schedule.cycles[0].len(), 3);
+        let err = schedule.check_cycles(&forest).unwrap_err().to_string();
+        assert!(
+            err.contains("0.md:2 -> 0.md:4 -> 0.md:3 -> 0.md:2"),
+            "{err}"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,112 @@
+use crate::{answer, fail, tree, util};
+
+// Free-text needle, matched against the full content of a Node, both its Meta and Data Parts
+#[derive(Debug)]
+pub enum Needle {
+    // Case-insensitive substring
+    Text(String),
+    Regex(regex::Regex),
+}
+
+impl Needle {
+    pub fn new(s: &str, is_regex: bool) -> util::Result<Needle> {
+        if s.is_empty() {
+            fail!("Expected a non-empty search needle");
+        }
+        let needle = if is_regex {
+            match regex::Regex::new(s) {
+                Ok(re) => Needle::Regex(re),
+                Err(err) => fail!("Could not parse regex '{}': {}", s, err),
+            }
+        } else {
+            Needle::Text(s.to_lowercase())
+        };
+        Ok(needle)
+    }
+
+    pub fn matches(&self, haystack: &str) -> bool {
+        match self {
+            Needle::Text(text) => haystack.to_lowercase().contains(text),
+            Needle::Regex(re) => re.is_match(haystack),
+        }
+    }
+}
+
+pub fn search(forest: &tree::Forest, needle: &Needle) -> util::Result<answer::Answer> {
+    let mut answer = answer::Answer::new();
+
+    forest.dfs(|tree, node| {
+        let content: String = node.content(tree);
+
+        if needle.matches(&content) {
+            answer.add(answer::Location {
+                filename: tree.filename.clone(),
+                line_nr: node.line_ix.unwrap_or(0) + 1,
+                org: node.org.to_string_with(&forest.env.calendar),
+                ctx: node.ctx.to_string_with(&forest.env.calendar),
+                content,
+                prio: node.prio(),
+                ..Default::default()
+            });
+        }
+        Ok(())
+    })?;
+
+    Ok(answer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::builder;
+
+    #[test]
+    fn test_search() -> util::Result<()> {
+        let mut builder = builder::Builder::new();
+        let forest = builder.create_forest_from_md(&[
+            "# Plan &!:status:~status\n- Write the README &todo\n- Fix parser bug &done",
+            "- Review readme\n- Release v1.2 &todo",
+        ])?;
+
+        let scns = [
+            ("readme", false, vec!["0.md:2", "1.md:1"]),
+            ("README", false, vec!["0.md:2", "1.md:1"]),
+            ("&todo", false, vec!["0.md:2", "1.md:2"]),
+            ("bug &done", false, vec!["0.md:3"]),
+            ("unknown", false, vec![]),
+            (r"v\d+\.\d+", true, vec!["1.md:2"]),
+            ("^- Fix", true, vec!["0.md:3"]),
+            ("readme", true, vec!["1.md:1"]),
+            ("(?i)readme", true, vec!["0.md:2", "1.md:1"]),
+        ];
+
+        for (s, is_regex, exp) in scns {
+            let answer = search(&forest, &Needle::new(s, is_regex)?)?;
+            let mut locations = Vec::new();
+            answer.each_location(|location, _meta| {
+                locations.push(format!(
+                    "{}:{}",
+                    location.filename.display(),
+                    location.line_nr
+                ));
+            });
+            assert_eq!(locations, exp, "{s}");
+        }
+
+        // Items without a Prio get the same default as in query::search
+        let forest = builder.create_forest_from_md(&[
+            "# Plan &!:prio:~priority\n- &!:est:~duration\n- Spec &prio:A2 &est:1w\n- Docs",
+        ])?;
+        let answer = search(&forest, &Needle::new("^- [A-Z]", true)?)?;
+        let mut found = Vec::new();
+        answer.each_location(|location, _meta| {
+            found.push(format!("{} {}", location.prio, location.org));
+        });
+        assert_eq!(found, vec!["A2 :prio:A2 :est:1w", "c0 "]);
+
+        assert!(Needle::new("", false).is_err());
+        assert!(Needle::new("(unclosed", true).is_err());
+
+        Ok(())
+    }
+}
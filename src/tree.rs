@@ -1,6 +1,12 @@
 pub mod builder;
+pub mod indent;
+pub mod index;
 pub mod md;
+pub mod mm;
+pub mod orgmode;
 pub mod src;
+#[cfg(test)]
+pub mod test_util;
 
 use crate::{amp, fail, rubr::naft, util};
 use std::{collections, path};
@@ -9,7 +15,6 @@ use std::{collections, path};
 // &next: use ignore.Tree to iterate and populate a tree.Forest
 // &next: interconnect Forest and compute Node.reachables
 // &next: distribute attributes from root to leaf
-// &next: aggregate data from leat to root
 #[derive(Default, Debug)]
 pub struct Forest {
     files: collections::BTreeMap<path::PathBuf, usize>,
@@ -17,6 +22,10 @@ pub struct Forest {
     roots: Vec<usize>,
     names: Vec<String>,
     pub defs: amp::Paths,
+    pub def_origins: Vec<NodeIx>, // Node where each of Forest.defs originates from
+    pub ids: collections::BTreeMap<amp::Id, Vec<NodeIx>>, // Nodes carrying each `&#N` Id, more than one is a duplicate
+    pub index: index::Index,
+    pub env: amp::Env, // Used to interpret typed Parts, eg relative Dates
 }
 
 // Represents a single file or folder
@@ -37,13 +46,28 @@ pub struct Node {
     pub parts: Vec<Part>,
     pub line_ix: Option<u64>,
     pub tree_ix: usize,
+    pub node_ix: usize,
     childs: Vec<usize>,     // Ancestral links to Nodes within the same Tree
     pub links: Vec<usize>,  // Direct links to other Trees
     reachables: Vec<usize>, // All other Trees that are recursively reachable
 
     pub def: Option<amp::Path>,
+    pub is_def_origin: bool, // Node.def was specified here and not inherited from a parent
     pub org: amp::Paths,
     pub ctx: amp::Paths,
+    pub checkbox: Option<&'static str>, // Status name from a Markdown task-list checkbox or Org-mode keyword, added to Node.org by resolve_org
+
+    pub deps: amp::Paths,     // Paths marked as dependency with a trailing `&`
+    pub prereqs: Vec<NodeIx>, // Nodes that define a dependency from Node.deps
+
+    pub agg: Aggregate,
+}
+
+// Identifies a Node within a Forest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct NodeIx {
+    pub tree_ix: usize,
+    pub node_ix: usize,
 }
 
 #[derive(Debug, Clone)]
@@ -65,6 +89,8 @@ pub enum Format {
     Folder,
     Markdown,
     MindMap,
+    Amp, // Line-oriented metadata with indentation-based nesting
+    OrgMode,
     SourceCode {
         comment: &'static str,
     },
@@ -88,8 +114,9 @@ impl Forest {
         self.files.insert(tree.filename.clone(), ix);
 
         tree.ix = self.trees.len();
-        for node in &mut tree.nodes {
+        for (node_ix, node) in tree.nodes.iter_mut().enumerate() {
             node.tree_ix = tree.ix;
+            node.node_ix = node_ix;
         }
         self.trees.push(tree);
 
@@ -148,6 +175,48 @@ impl Forest {
         Ok(())
     }
 
+    pub fn node(&self, ix: &NodeIx) -> Option<&Node> {
+        self.trees
+            .get(ix.tree_ix)
+            .and_then(|tree| tree.nodes.get(ix.node_ix))
+    }
+
+    // Formats as `filename:line_nr`
+    pub fn location(&self, ix: &NodeIx) -> String {
+        match self.trees.get(ix.tree_ix) {
+            Some(tree) => {
+                let line_nr = tree
+                    .nodes
+                    .get(ix.node_ix)
+                    .and_then(|node| node.line_ix)
+                    .unwrap_or(0)
+                    + 1;
+                format!("{}:{}", tree.filename.display(), line_nr)
+            }
+            None => "?".into(),
+        }
+    }
+
+    // A Node is blocked when any of its prerequisites is not closed yet
+    pub fn is_blocked(&self, node: &Node) -> bool {
+        node.prereqs.iter().any(|ix| {
+            !self
+                .node(ix)
+                .map(|prereq| prereq.is_closed())
+                .unwrap_or(false)
+        })
+    }
+
+    // The first Node carrying `id`, duplicates are reported when building the Forest
+    pub fn find_id(&self, id: &amp::Id) -> Option<&NodeIx> {
+        self.ids.get(id).and_then(|ixs| ixs.first())
+    }
+
+    // One more than the largest Id in use
+    pub fn next_id(&self) -> amp::Id {
+        amp::Id(self.ids.keys().last().map_or(1, |id| id.0 + 1))
+    }
+
     pub fn connect(&mut self) -> util::Result<()> {
         Ok(())
     }
@@ -219,6 +288,26 @@ impl Tree {
     pub fn root(&self) -> &Node {
         &self.nodes[self.root_ix]
     }
+
+    // Copy of self.content with `suffix` inserted before the line ending of line `line_ix`.
+    // For Org-mode headings, `suffix` is inserted before the trailing tags to keep them recognizable.
+    pub fn append_to_line(&self, line_ix: usize, suffix: &str) -> Option<String> {
+        let mut offset = 0;
+        for (ix, line) in self.content.split_inclusive('\n').enumerate() {
+            if ix == line_ix {
+                let text = line.trim_end_matches(['\n', '\r']);
+                let end = match self.format {
+                    Format::OrgMode => orgmode::heading_end(text),
+                    _ => text.len(),
+                };
+                let mut content = self.content.clone();
+                content.insert_str(offset + end, suffix);
+                return Some(content);
+            }
+            offset += line.len();
+        }
+        None
+    }
     pub fn root_mut(&mut self) -> &mut Node {
         &mut self.nodes[self.root_ix]
     }
@@ -324,8 +413,84 @@ impl naft::ToNaft for Tree {
 
 pub type Range = std::ops::Range<usize>;
 
-#[derive(Debug)]
-pub enum Aggregate {}
+// Data rolled up from a Node and all its descendants, including linked Trees
+#[derive(Debug, Default, Clone)]
+pub struct Aggregate {
+    pub done: u32,              // Items with a closed Status
+    pub total: u32,             // Items with a Status
+    pub left: amp::Duration,    // Summed expected Duration of open items
+    pub variance: f64,          // Summed variance of the Estimates for open items, in minutes^2
+    pub due: Option<amp::Date>, // Earliest due date of open items
+}
+
+impl Aggregate {
+    // Contribution of `node` itself, inherited data from Node.ctx is not taken into account
+    pub fn from_node(node: &Node) -> Aggregate {
+        let mut agg = Aggregate::default();
+        if let Some(status) = node.org.data.iter().find_map(|path| path.get_status()) {
+            agg.total = 1;
+            if !status.is_open {
+                agg.done = 1;
+            }
+        }
+        if node.is_open() {
+            if let Some(estimate) = node.org.data.iter().find_map(|path| path.get_estimate()) {
+                agg.left = estimate.expected();
+                agg.variance = estimate.variance();
+            }
+            agg.due = node
+                .org
+                .data
+                .iter()
+                .filter(|path| path.has_tag("due"))
+                .find_map(|path| path.get_date().cloned());
+        }
+        agg
+    }
+
+    pub fn merge(&mut self, rhs: &Aggregate) {
+        self.done += rhs.done;
+        self.total += rhs.total;
+        self.left += &rhs.left;
+        self.variance += rhs.variance;
+        if let Some(due) = &rhs.due {
+            if self.due.as_ref().is_none_or(|self_due| due < self_due) {
+                self.due = Some(due.clone());
+            }
+        }
+    }
+
+    // Eg: `3/7 done, 2d4h left, due 2024-10-01`, Durations are formatted according to `calendar`
+    pub fn to_string_with(&self, calendar: &amp::Calendar) -> String {
+        let mut s = String::new();
+        let mut prefix = "";
+        if self.total > 0 {
+            s.push_str(&format!("{}/{} done", self.done, self.total));
+            prefix = ", ";
+        }
+        if self.left.minutes() > 0 {
+            s.push_str(&format!("{prefix}{}", self.left.to_string_with(calendar)));
+            // Independent Estimates: the standard deviation is the root of the summed variances
+            let spread = self.variance.sqrt().round() as u32;
+            if spread > 0 {
+                let spread = amp::Duration::from_calendar(0, 0, 0, spread, calendar);
+                s.push_str(&format!(" ±{}", spread.to_string_with(calendar)));
+            }
+            s.push_str(" left");
+            prefix = ", ";
+        }
+        if let Some(due) = &self.due {
+            s.push_str(&format!("{prefix}due {due}"));
+        }
+        s
+    }
+}
+
+impl std::fmt::Display for Aggregate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_string_with(&amp::Calendar::default()))
+    }
+}
 
 impl Part {
     fn new(range: &Range, kind: Kind) -> Part {
@@ -337,6 +502,51 @@ impl Part {
 }
 
 impl Node {
+    pub fn ix(&self) -> NodeIx {
+        NodeIx {
+            tree_ix: self.tree_ix,
+            node_ix: self.node_ix,
+        }
+    }
+
+    // The Status from Node.org takes precedence over the one inherited via Node.ctx
+    pub fn status(&self) -> Option<&amp::Status> {
+        self.org
+            .data
+            .iter()
+            .chain(self.ctx.data.iter())
+            .find_map(|path| path.get_status())
+    }
+
+    // The Prio inherited via Node.ctx, defaults to `c0` for Nodes without one
+    pub fn prio(&self) -> amp::Prio {
+        self.ctx
+            .data
+            .iter()
+            .find_map(|path| path.get_prio().cloned())
+            .unwrap_or_else(|| amp::Prio::new(5, 0))
+    }
+
+    // Concatenated text of all Parts, `tree` must be the Tree this Node belongs to
+    pub fn content(&self, tree: &Tree) -> String {
+        self.parts
+            .iter()
+            .filter_map(|part| tree.content.get(part.range.clone()))
+            .collect()
+    }
+
+    pub fn is_leaf(&self) -> bool {
+        self.childs.is_empty() && self.links.is_empty()
+    }
+
+    // Nodes without a Status are considered open
+    pub fn is_open(&self) -> bool {
+        self.status().map(|status| status.is_open).unwrap_or(true)
+    }
+    pub fn is_closed(&self) -> bool {
+        self.status().is_some_and(|status| !status.is_open)
+    }
+
     pub fn print(&self, content: &str, _format: &Format) {
         if let Some(line_ix) = self.line_ix {
             print!("{:<5}", line_ix + 1);
@@ -363,6 +573,10 @@ impl naft::ToNaft for Node {
         self.org.to_naft(b);
         b.set_ctx("ctx");
         self.ctx.to_naft(b);
+        if !self.deps.is_empty() {
+            b.set_ctx("deps");
+            self.deps.to_naft(b)?;
+        }
         b.reset_ctx();
         Ok(())
     }
@@ -1,6 +1,9 @@
 // &a1 &todo: MD here should apply to the whole Tree
 
-use crate::{amp, fail, fs, lex, path, tree, tree::md, tree::src, util};
+use crate::{
+    amp, fail, fs, lex, path, tree, tree::indent, tree::md, tree::mm, tree::orgmode, tree::src,
+    util,
+};
 use std::collections;
 use tracing::{error, info, span, trace, warn, Level};
 
@@ -14,28 +17,80 @@ type Part = tree::Part;
 pub struct Builder {
     lexer: lex::Lexer,
     md_tree: md::Tree,
+    mm_tree: mm::Tree,
+    indent_tree: indent::Tree,
+    org_tree: orgmode::Tree,
     src_trees: collections::BTreeMap<String, src::Tree>,
     amp_parser: amp::parse::Parser,
+    env: amp::Env,
 }
 impl Builder {
     pub fn new() -> Builder {
         Builder {
             lexer: lex::Lexer::new(),
             md_tree: md::Tree::new(),
+            mm_tree: mm::Tree::new(),
+            indent_tree: indent::Tree::new(),
+            org_tree: orgmode::Tree::new(),
             src_trees: Default::default(),
             amp_parser: amp::parse::Parser::new(),
+            env: amp::Env::default(),
         }
     }
 
+    // Forests built afterwards interpret typed Parts against `env`
+    pub fn with_env(mut self, env: amp::Env) -> Builder {
+        self.env = env;
+        self
+    }
+
     pub fn create_forest_from(&mut self, fs_forest: &mut fs::Forest) -> util::Result<Forest> {
         let mut forest = tree::Forest::new();
+        forest.env = self.env.clone();
         self.add_to_forest_recursive_(&path::Path::root(), 0, fs_forest, &mut forest)?;
+        self.run_passes(&mut forest)?;
+        Ok(forest)
+    }
+
+    // Derives all Node data from the Trees in `forest`, shared by all ways to create a Forest
+    fn run_passes(&mut self, forest: &mut Forest) -> util::Result<()> {
+        self.init_org_def(forest)?;
+        self.init_statuses(forest)?;
+        self.join_defs(forest)?;
+        self.resolve_org(forest)?;
+        self.init_ids(forest)?;
+        self.init_ctx(forest)?;
+        self.init_index(forest)?;
+        self.init_deps(forest)?;
+        self.init_aggregates(forest)?;
+        Ok(())
+    }
 
-        self.init_org_def(&mut forest)?;
-        self.join_defs(&mut forest)?;
-        self.resolve_org(&mut forest)?;
-        self.init_ctx(&mut forest)?;
+    // Builds a Forest from in-memory Markdown content, running the same passes as create_forest_from()
+    #[cfg(test)]
+    pub fn create_forest_from_md(&mut self, contents: &[&str]) -> util::Result<Forest> {
+        let filenames = (0..contents.len())
+            .map(|ix| format!("{ix}.md"))
+            .collect::<Vec<_>>();
+        let files = filenames
+            .iter()
+            .map(String::as_str)
+            .zip(contents.iter().copied())
+            .collect::<Vec<_>>();
+        self.create_forest_from_files(&files)
+    }
 
+    // Builds a Forest from in-memory (filename, content) pairs, the Format is derived from the filename
+    #[cfg(test)]
+    pub fn create_forest_from_files(&mut self, files: &[(&str, &str)]) -> util::Result<Forest> {
+        let mut forest = tree::Forest::new();
+        forest.env = self.env.clone();
+        for (filename, content) in files {
+            let filename = std::path::Path::new(filename);
+            let tree = self.create_tree(content, format_from_path(filename), filename);
+            forest.add(tree, 0)?;
+        }
+        self.run_passes(&mut forest)?;
         Ok(forest)
     }
 
@@ -52,7 +107,10 @@ impl Builder {
             trace!("{}:{}", filename.display(), node.line_ix.unwrap_or(0) + 1,);
 
             let m = match format {
-                Format::Markdown => Some(amp::parse::Match::Everywhere),
+                Format::Markdown | Format::MindMap | Format::OrgMode => {
+                    Some(amp::parse::Match::Everywhere)
+                }
+                Format::Amp => Some(amp::parse::Match::BareDefs),
                 Format::SourceCode { comment: _ } => Some(amp::parse::Match::OnlyStart),
                 _ => None,
             };
@@ -78,6 +136,9 @@ impl Builder {
                                             );
                                         }
                                         node.def = Some(path.clone());
+                                        node.is_def_origin = true;
+                                    } else if path.is_dependency {
+                                        node.deps.insert(path.clone());
                                     } else {
                                         node.org.insert(path.clone());
                                     }
@@ -150,9 +211,50 @@ impl Builder {
             if let Some(md_paths) = md_paths {
                 forest.trees[ix].root_mut().org = md_paths;
             }
-            forest.trees[ix].root_mut().def = md_def;
+            // A File can already have a def from its front matter
+            if md_def.is_some() {
+                let root = forest.trees[ix].root_mut();
+                root.is_def_origin = true;
+                root.def = md_def;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Registers Status definitions (`&!~status:NAME`) and removes them from Node.def
+    // This must happen before Node.org is resolved against the `~status` templates
+    fn init_statuses(&mut self, forest: &mut Forest) -> util::Result<()> {
+        let span = span!(Level::TRACE, "init_statuses");
+        let _g = span.enter();
+
+        let mut statuses = forest.env.statuses.clone();
+
+        forest.each_node_mut(|node, _content, _format, filename| {
+            if let Some(def) = node.def.take_if(|def| def.is_status_def()) {
+                node.is_def_origin = false;
+                match amp::StatusDef::try_from(&def) {
+                    Ok(status_def) => {
+                        info!("Found {} in '{}'", &status_def, filename.display());
+                        statuses.insert(status_def);
+                    }
+                    Err(err) => error!(
+                        "Invalid Status definition in '{}:{}': {}",
+                        filename.display(),
+                        node.line_ix.unwrap_or(0) + 1,
+                        &err
+                    ),
+                }
+            }
+            Ok(())
+        })?;
+
+        for (from, to) in statuses.unknown_transitions() {
+            warn!("Status '{}' can move to unknown Status '{}'", from, to);
         }
 
+        forest.env.statuses = statuses;
+
         Ok(())
     }
 
@@ -179,7 +281,7 @@ impl Builder {
             })
         })?;
 
-        // Interpret special Tags for defs: ~status, ~priority, ~date and ~duration
+        // Interpret special Tags for defs: ~status, ~priority, ~date, ~duration, ~scope, ~risk and ~effort
         forest.each_node_mut(
             |node: &mut Node, _content: &str, _format: &Format, _filename: &std::path::PathBuf| {
                 if let Some(def) = node.def.as_mut() {
@@ -200,6 +302,9 @@ impl Builder {
                                     part,
                                     &mut amp::Part::Duration(amp::Duration::new(0, 0, 0, 0)),
                                 ),
+                                "~scope" => *part = amp::Part::Scope(amp::Scope::Must),
+                                "~risk" => *part = amp::Part::Risk(amp::Risk::Low),
+                                "~effort" => *part = amp::Part::Effort(amp::Effort::M),
                                 _ => {}
                             }
                         }
@@ -216,47 +321,118 @@ impl Builder {
         let span = span!(Level::INFO, "resolve_org");
         let _g = span.enter();
 
-        // Collect all defined Keys
+        // Collect all defined Keys, together with the Node where they originate from
         let mut defs = amp::Paths::new();
-        forest.each_node(|_tree, node| {
-            if let Some(def) = &node.def {
-                defs.insert(def.clone());
+        let mut def_origins = Vec::new();
+        for (tree_ix, tree) in forest.trees.iter().enumerate() {
+            for (node_ix, node) in tree.nodes.iter().enumerate() {
+                if let Some(def) = &node.def {
+                    if node.is_def_origin && !defs.data.contains(def) {
+                        defs.insert(def.clone());
+                        def_origins.push(tree::NodeIx { tree_ix, node_ix });
+                    }
+                }
             }
-            Ok(())
-        })?;
+        }
+        let def_locations = def_origins
+            .iter()
+            .map(|ix| forest.location(ix))
+            .collect::<Vec<_>>();
+
+        let env = forest.env.clone();
+        let resolve = |path: &amp::Path, location: &str| -> Option<amp::Path> {
+            match defs.resolve(path, &env) {
+                amp::Resolution::Unique(mut abs_path) => {
+                    info!("Resolved {} into {} for {}", path, &abs_path, location);
+                    abs_path.is_definition = false;
+                    Some(abs_path)
+                }
+                amp::Resolution::Unresolved if path.is_attribute() => {
+                    // Attributes survive without a def
+                    info!("Kept attribute {} for {}", path, location);
+                    Some(path.clone())
+                }
+                amp::Resolution::Unresolved => {
+                    warn!("Could not resolve '{}' for '{}'", path, location);
+                    None
+                }
+                amp::Resolution::Ambiguous(ixs) => {
+                    // Only this reference is left unresolved, the rest of the Forest remains usable
+                    let competing = ixs
+                        .iter()
+                        .map(|&ix| {
+                            format!(
+                                "'{}' ({})",
+                                defs.data[ix].to_template_string(),
+                                &def_locations[ix]
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    error!(
+                        "Ambiguous reference '{}' for '{}' matches with {}",
+                        path, location, competing
+                    );
+                    None
+                }
+            }
+        };
 
         forest.each_node_mut(
             |node: &mut Node, _content: &str, _format: &Format, filename: &std::path::PathBuf| {
+                let location = format!("{}:{}", filename.display(), node.line_ix.unwrap_or(0) + 1);
+
                 if !node.org.is_empty() {
                     let mut abs_paths = amp::Paths::new();
 
                     for path in &node.org.data {
                         // We always resolve to make sure consistent Paths are used with the correct exclusive flag
-                        if let Some(mut abs_path) = defs.resolve(path) {
-                            info!(
-                                "Resolved {} into {} for {}",
-                                path,
-                                &abs_path,
-                                filename.display()
-                            );
+                        if let Some(abs_path) = resolve(path, &location) {
                             if abs_paths.has_variant(&abs_path) {
                                 fail!("Found variant for '{}' in '{}'", &abs_path, &abs_paths);
                             } else {
-                                abs_path.is_definition = false;
                                 abs_paths.insert(abs_path);
                             }
-                        } else {
-                            warn!("Could not resolve '{}' for '{}'", path, filename.display());
                         }
                     }
 
                     std::mem::swap(&mut node.org, &mut abs_paths);
                 }
+
+                // An explicit Status takes precedence over a checkbox
+                if let Some(status) = node.checkbox.and_then(|name| env.statuses.parse(name).ok()) {
+                    if node.org.data.iter().all(|path| path.get_status().is_none()) {
+                        let path = amp::Path {
+                            parts: vec![amp::Part::Status(status)],
+                            ..amp::Path::new(false, false, &[])
+                        };
+                        // Without a `~status` definition, the checkbox Status is kept as-is
+                        let abs_path = match defs.resolve(&path, &env) {
+                            amp::Resolution::Unique(mut abs_path) => {
+                                abs_path.is_definition = false;
+                                abs_path
+                            }
+                            _ => path,
+                        };
+                        node.org.insert(abs_path);
+                    }
+                }
+
+                if !node.deps.is_empty() {
+                    let mut abs_deps = amp::Paths::new();
+                    for path in &node.deps.data {
+                        if let Some(abs_path) = resolve(path, &location) {
+                            abs_deps.insert(abs_path);
+                        }
+                    }
+                    std::mem::swap(&mut node.deps, &mut abs_deps);
+                }
                 Ok(())
             },
         )?;
 
         forest.defs = defs;
+        forest.def_origins = def_origins;
 
         Ok(())
     }
@@ -318,47 +494,207 @@ impl Builder {
         Ok(())
     }
 
-    pub fn create_tree_from_path(&mut self, path: &std::path::Path) -> util::Result<Tree> {
-        let content = std::fs::read_to_string(path)?;
+    // Links each Node.deps to the Nodes where the corresponding def originates from
+    fn init_deps(&mut self, forest: &mut Forest) -> util::Result<()> {
+        let span = span!(Level::TRACE, "init_deps");
+        let _g = span.enter();
 
-        let format = path
-            .extension()
-            .and_then(|ext| {
-                let format = match &ext.to_string_lossy() as &str {
-                    // &todo: Treat `.amp` as its own file format
-                    "md" | "amp" => Format::Markdown,
-                    "mm" => Format::MindMap,
-                    "rb" | "py" | "sh" => Format::SourceCode { comment: "#" },
-                    "h" | "c" | "hpp" | "cpp" | "rs" | "chai" => {
-                        Format::SourceCode { comment: "//" }
+        // Only the Parts are used as key: Node.def is a definition while Node.deps are not
+        let mut origins = collections::BTreeMap::<Vec<amp::Part>, Vec<tree::NodeIx>>::new();
+        for (tree_ix, tree) in forest.trees.iter().enumerate() {
+            for (node_ix, node) in tree.nodes.iter().enumerate() {
+                if let Some(def) = &node.def {
+                    if node.is_def_origin {
+                        origins
+                            .entry(def.parts.clone())
+                            .or_default()
+                            .push(tree::NodeIx { tree_ix, node_ix });
                     }
-                    _ => Format::Unknown,
-                };
-                Some(format)
-            })
-            .unwrap_or(Format::Unknown);
+                }
+            }
+        }
 
-        let mut tree = self.create_tree_from_str(&content, format);
-        tree.filename = path.into();
+        forest.each_tree_mut(|tree| {
+            for node in &mut tree.nodes {
+                node.prereqs.clear();
+                for dep in &node.deps.data {
+                    if let Some(ixs) = origins.get(&dep.parts) {
+                        node.prereqs.extend(ixs.iter().copied());
+                    } else {
+                        warn!(
+                            "Could not find the item for dependency '{}' in '{}'",
+                            dep,
+                            tree.filename.display()
+                        );
+                    }
+                }
+            }
+            Ok(())
+        })?;
 
-        Ok(tree)
+        Ok(())
+    }
+
+    fn init_index(&mut self, forest: &mut Forest) -> util::Result<()> {
+        let span = span!(Level::TRACE, "init_index");
+        let _g = span.enter();
+
+        forest.index = tree::index::Index::new(forest);
+
+        Ok(())
+    }
+
+    // Collects the `&#N` Ids from node.org, reporting Ids that are used more than once
+    fn init_ids(&mut self, forest: &mut Forest) -> util::Result<()> {
+        let span = span!(Level::TRACE, "init_ids");
+        let _g = span.enter();
+
+        forest.ids.clear();
+        for tree in &forest.trees {
+            // &spec: a Folder copies the Ids from its `&.md`, they belong to the `&.md` Node only
+            if matches!(tree.format, Format::Folder) {
+                continue;
+            }
+            for node in &tree.nodes {
+                for id in node.org.data.iter().filter_map(|path| path.get_id()) {
+                    let ixs = forest.ids.entry(id).or_default();
+                    if !ixs.contains(&node.ix()) {
+                        ixs.push(node.ix());
+                    }
+                }
+            }
+        }
+
+        for (id, ixs) in &forest.ids {
+            if ixs.len() > 1 {
+                let locations = ixs
+                    .iter()
+                    .map(|ix| forest.location(ix))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                error!("Found duplicate id {} at {}", id, locations);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Rolls up Node.agg from leaf to root, and from linked Trees into the Node that links to them
+    fn init_aggregates(&mut self, forest: &mut Forest) -> util::Result<()> {
+        let span = span!(Level::TRACE, "init_aggregates");
+        let _g = span.enter();
+
+        // Linked Trees are added to the Forest before the Folder that links to them,
+        // hence they are already aggregated when we encounter them
+        for tree_ix in 0..forest.trees.len() {
+            let linked_aggs = forest.trees[tree_ix]
+                .nodes
+                .iter()
+                .map(|node| {
+                    let mut agg = tree::Aggregate::default();
+                    for &link_ix in &node.links {
+                        if link_ix < tree_ix {
+                            agg.merge(&forest.trees[link_ix].root().agg);
+                        } else {
+                            warn!("Skipping aggregation of Tree {} into {}", link_ix, tree_ix);
+                        }
+                    }
+                    agg
+                })
+                .collect::<Vec<_>>();
+
+            let tree = &mut forest.trees[tree_ix];
+            for (node, linked_agg) in tree.nodes.iter_mut().zip(linked_aggs) {
+                node.agg = tree::Aggregate::from_node(node);
+                node.agg.merge(&linked_agg);
+            }
+            // &spec: the Estimate of a parent is its own work and adds to that of its children
+            tree.leaf_to_root(|src, dst| src.agg.merge(&dst.agg));
+        }
+
+        Ok(())
+    }
+
+    pub fn create_tree_from_path(&mut self, path: &std::path::Path) -> util::Result<Tree> {
+        let content = std::fs::read_to_string(path)?;
+
+        Ok(self.create_tree(&content, format_from_path(path), path))
+    }
+
+    #[cfg(test)]
+    pub fn create_tree_from_str(&mut self, content: &str, format: Format) -> Tree {
+        self.create_tree(content, format, std::path::Path::new(""))
     }
 
     // Creates a flat tree with lines split on '\n'
+    // `filename` is stored in the Tree and used when reporting parse problems
     // &next: parse content into meronomy, taking Format into account
     // &next: strip whitespace at the end of `main`
     // &todo: make this fail when the parsing Tree cannot be created
-    pub fn create_tree_from_str(&mut self, content: &str, format: Format) -> Tree {
+    fn create_tree(&mut self, content: &str, format: Format, filename: &std::path::Path) -> Tree {
         let mut tree = Tree::new();
+        tree.filename = filename.into();
         tree.content = content.into();
         tree.format = format;
 
         self.lexer.tokenize(content);
 
         match tree.format {
-            Format::MindMap => todo!("Implement XML-MM parsing"),
+            Format::Amp => {
+                self.indent_tree.init(content);
+
+                tree.nodes
+                    .resize_with(self.indent_tree.nodes.len(), Node::default);
+
+                for (ix, indent_node) in self.indent_tree.nodes.iter().enumerate() {
+                    let node = &mut tree.nodes[ix];
+                    node.line_ix = Some(indent_node.line_ix);
+                    node.parts = indent_node.parts.clone();
+                    node.childs = indent_node.childs.clone();
+                }
+            }
+            Format::OrgMode => {
+                self.org_tree.init(content);
+
+                tree.nodes
+                    .resize_with(self.org_tree.nodes.len(), Node::default);
+
+                for (ix, org_node) in self.org_tree.nodes.iter().enumerate() {
+                    let node = &mut tree.nodes[ix];
+                    node.line_ix = Some(org_node.line_ix);
+                    node.parts = org_node.parts.clone();
+                    node.childs = org_node.childs.clone();
+                    node.checkbox = org_node.status;
+                    // Tags and timestamps are resolved by resolve_org, just like AMP from the Meta Parts
+                    for path in &org_node.paths {
+                        node.org.insert(path.clone());
+                    }
+                }
+            }
+            Format::MindMap => {
+                if let Err(err) = self.mm_tree.init(content) {
+                    error!(
+                        "Could not parse mind-map '{}': {}",
+                        tree.filename.display(),
+                        err
+                    );
+                }
+
+                // Parts refer to the decoded node texts and not to the XML itself
+                tree.content = self.mm_tree.content.clone();
+
+                tree.nodes
+                    .resize_with(self.mm_tree.nodes.len(), Node::default);
+
+                for (ix, mm_node) in self.mm_tree.nodes.iter().enumerate() {
+                    let node = &mut tree.nodes[ix];
+                    node.line_ix = Some(mm_node.line_ix);
+                    node.parts = mm_node.parts.clone();
+                    node.childs = mm_node.childs.clone();
+                }
+            }
             Format::Markdown => {
-                self.md_tree.init(&self.lexer.tokens);
+                self.md_tree.init(&self.lexer.tokens, content);
 
                 tree.nodes
                     .resize_with(self.md_tree.nodes.len(), || Node::default());
@@ -366,6 +702,7 @@ impl Builder {
                 for (ix, md_node) in self.md_tree.nodes.iter().enumerate() {
                     let node = &mut tree.nodes[ix];
                     node.line_ix = Some(md_node.line_ix);
+                    node.checkbox = md_node.checkbox;
 
                     // Combine parts of same kind to ensure amp.Parser can see all metadata at once
                     for md_part in &md_node.parts {
@@ -382,6 +719,8 @@ impl Builder {
                         node.childs.push(*child_ix);
                     }
                 }
+
+                self.add_front_matter(&mut tree);
             }
             Format::SourceCode { comment } => {
                 if !self.src_trees.contains_key(comment) {
@@ -434,6 +773,42 @@ impl Builder {
         tree
     }
 
+    // Attaches the front matter to the root, as if it was written as AMP:
+    // - `tags: [a, b]` as `&a &b`
+    // - `status: done` as `&done`
+    // - `due: 2024-10-01` and `prio: a1` as `&due:2024-10-01` and `&prio:a1`
+    // - `def: :proj:amp` as `&!:proj:amp`
+    fn add_front_matter(&self, tree: &mut Tree) {
+        let root = tree.root_mut();
+        for (key, values) in &self.md_tree.front_matter {
+            for value in values {
+                match key.as_str() {
+                    "tags" | "status" => {
+                        root.org.insert(amp::Path::new(false, false, &[value]));
+                    }
+                    "due" | "prio" => {
+                        root.org.insert(amp::Path::new(false, false, &[key, value]));
+                    }
+                    "def" => {
+                        let value = value.trim_start_matches(['&', '!']);
+                        match amp::Path::try_from(value) {
+                            Ok(mut def) if root.def.is_none() => {
+                                def.is_definition = true;
+                                root.def = Some(def);
+                                root.is_def_origin = true;
+                            }
+                            Ok(_) => error!("Found double definition '{}' in front matter", value),
+                            Err(err) => {
+                                error!("Could not parse def '{}' from front matter: {}", value, err)
+                            }
+                        }
+                    }
+                    _ => trace!("Skipping front matter key '{}'", key),
+                }
+            }
+        }
+    }
+
     fn add_to_forest_recursive_(
         &mut self,
         parent: &path::Path,
@@ -476,9 +851,24 @@ impl Builder {
     }
 }
 
+fn format_from_path(path: &std::path::Path) -> Format {
+    path.extension()
+        .map(|ext| match &ext.to_string_lossy() as &str {
+            "md" => Format::Markdown,
+            "amp" => Format::Amp,
+            "org" => Format::OrgMode,
+            "mm" => Format::MindMap,
+            "rb" | "py" | "sh" => Format::SourceCode { comment: "#" },
+            "h" | "c" | "hpp" | "cpp" | "rs" | "chai" => Format::SourceCode { comment: "//" },
+            _ => Format::Unknown,
+        })
+        .unwrap_or(Format::Unknown)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{query, rank, tree::test_util};
 
     #[test]
     fn test_api() -> util::Result<()> {
@@ -501,4 +891,505 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_deps() -> util::Result<()> {
+        let mut builder = Builder::new();
+        let forest = builder.create_forest_from_md(&[
+            "# Status &!:status:~status\n- Parser &!:parser &todo\n- Docs &parser& &todo",
+            "- Release &parser& &todo\n- Cleanup &todo",
+        ])?;
+
+        let is_blocked =
+            |content: &str| test_util::find(&forest, content).map(|node| forest.is_blocked(node));
+
+        assert_eq!(is_blocked("Parser"), Some(false));
+        assert_eq!(is_blocked("Docs"), Some(true));
+        assert_eq!(is_blocked("Release"), Some(true));
+        assert_eq!(is_blocked("Cleanup"), Some(false));
+
+        let forest = builder.create_forest_from_md(&[
+            "# Status &!:status:~status\n- Parser &!:parser &done\n- Docs &parser& &todo",
+        ])?;
+        let mut blocked = Vec::new();
+        forest.each_node(|_tree, node| {
+            if !node.prereqs.is_empty() {
+                blocked.push(forest.is_blocked(node));
+            }
+            Ok(())
+        })?;
+        assert_eq!(blocked, vec![false]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_org_canonical() -> util::Result<()> {
+        let mut builder = Builder::new();
+        let forest = builder.create_forest_from_md(&[
+            "# Stuff &!:MyProject:Zo\u{eb}\n- a &myproject:ZOE\u{308}\n- b &ZO\u{cb}",
+        ])?;
+
+        let orgs = test_util::orgs(&forest);
+        assert_eq!(orgs, vec![":MyProject:Zo\u{eb}", ":MyProject:Zo\u{eb}"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_org_ambiguous() -> util::Result<()> {
+        let mut builder = Builder::new();
+        let defs = "# A &!:a\n## X &!x\n# B &!:b\n## X &!x\n## Y &!y";
+
+        // Ambiguous references are reported and left unresolved, the other references still resolve
+        let forest = builder.create_forest_from_md(&[defs, "- a &x\n- b &y\n- c &a:x\n- d &x"])?;
+        let mut orgs = Vec::new();
+        forest.each_node(|tree, node| {
+            if tree.filename.to_string_lossy() == "1.md" {
+                orgs.push(node.org.to_string());
+            }
+            Ok(())
+        })?;
+        // The root and bullet-level-0 Nodes
+        assert_eq!(orgs, vec!["", "", "", ":b:y", ":a:x", ""]);
+
+        assert_eq!(forest.defs.data.len(), 5);
+        assert_eq!(forest.location(&forest.def_origins[1]), "0.md:2");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_org_templates() -> util::Result<()> {
+        let mut builder = Builder::new().with_env(amp::Env {
+            today: amp::Date::new(2024, 12, 31),
+            ..Default::default()
+        });
+        // Typed templates only accept a value after their key, a bare `&monday` is not ambiguous
+        let forest = builder.create_forest_from_md(&[
+            "# Plan &!:due:~date\n- &!:scheduled:~date\n- &!:prio:~priority\n\
+            - Meet on &monday\n- a &due:monday\n- b &a1\n- c &prio:a1",
+        ])?;
+
+        let orgs = test_util::orgs(&forest);
+        assert_eq!(orgs, [":due:2025-01-06", ":prio:a1"]);
+
+        let def = forest
+            .defs
+            .data
+            .iter()
+            .find(|def| def.has_tag("due"))
+            .unwrap();
+        assert_eq!(def.to_template_string(), "!:due:~date");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_statuses() -> util::Result<()> {
+        let mut builder = Builder::new();
+        let forest = builder.create_forest_from_md(&[
+            "# Status &!:status:~status\n- &!~status:shelved:closed\n- A &shelved\n- B &wait",
+        ])?;
+
+        let mut statuses = Vec::new();
+        forest.each_node(|_tree, node| {
+            if let Some(status) = node.status() {
+                statuses.push((status.to_string(), node.is_open()));
+            }
+            Ok(())
+        })?;
+        assert_eq!(
+            statuses,
+            vec![("shelved".to_string(), false), ("wait".to_string(), true)]
+        );
+        assert!(forest.defs.data.iter().all(|def| !def.is_status_def()));
+
+        // Each Forest has its own Statuses: `shelved` is unknown here, configured Statuses extend the built-in ones
+        let mut env = amp::Env::default();
+        env.statuses
+            .insert(amp::StatusDef::new("review", false, &[]));
+        let mut builder = Builder::new().with_env(env);
+        let other = builder.create_forest_from_md(&[
+            "# Status &!:status:~status\n- A &shelved\n- B &review\n- C &done",
+        ])?;
+
+        let mut statuses = Vec::new();
+        other.each_node(|_tree, node| {
+            if let Some(status) = node.status() {
+                statuses.push((status.to_string(), node.is_open()));
+            }
+            Ok(())
+        })?;
+        assert_eq!(
+            statuses,
+            vec![("review".to_string(), false), ("done".to_string(), false)]
+        );
+        assert!(other.env.statuses.get("shelved").is_none());
+        assert!(forest.env.statuses.get("review").is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_value() -> util::Result<()> {
+        let mut builder = Builder::new();
+        let forest = builder.create_forest_from_md(&["# Team &!:team:Owner
+- a &owner=alice
+- b &owner=bob &effort=3h"])?;
+
+        let orgs = test_util::orgs(&forest);
+        assert_eq!(orgs, vec![":team:Owner=alice", ":team:Owner=bob effort=3h"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_amp_format() -> util::Result<()> {
+        let mut builder = Builder::new();
+        let forest = builder.create_forest_from_files(&[
+            (
+                "defs.amp",
+                "!:status:~status\n!:bug\n&!:feature\nRelease &todo\n  Parser &bug\n    Fix it!\n  Lexer",
+            ),
+            ("plan.md", "# Plan\n- A &feature &done"),
+        ])?;
+
+        assert_eq!(forest.defs.data.len(), 3);
+
+        let scns = [
+            ("Release &todo", ":status:todo", ":status:todo"),
+            ("Parser &bug", ":bug", ":bug :status:todo"),
+            ("Fix it!", "", ":bug :status:todo"),
+            ("Lexer", "", ":status:todo"),
+        ];
+        for (content, exp_org, exp_ctx) in scns {
+            let Some(node) = test_util::find(&forest, content) else {
+                fail!("Could not find '{}'", content);
+            };
+            assert_eq!(node.org.to_string(), exp_org, "{content}");
+            assert_eq!(node.ctx.to_string(), exp_ctx, "{content}");
+        }
+
+        // Definitions from the `.amp` file are used to resolve Paths in other files
+        let node = forest.trees[1].nodes.last().unwrap();
+        assert_eq!(node.org.to_string(), ":feature :status:done");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_orgmode() -> util::Result<()> {
+        let mut builder = Builder::new();
+        let forest = builder.create_forest_from_files(&[
+            (
+                "defs.md",
+                "# Defs &!:status:~status\n- &!:bug\n- &!:due:~date\n- &!:scheduled:~date",
+            ),
+            (
+                "plan.org",
+                "* Roadmap\n** TODO Fix parser :bug:\n   DEADLINE: <2024-10-01 Tue>\n\
+                ** DONE Release &todo\n   SCHEDULED: <2024-09-01 Sun>\n** Review",
+            ),
+        ])?;
+
+        let tree = &forest.trees[1];
+        let orgs = tree
+            .nodes
+            .iter()
+            .map(|node| node.org.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            orgs,
+            [
+                "",
+                "",
+                ":bug :due:2024-10-01 :status:todo",
+                ":scheduled:2024-09-01 :status:todo",
+                ""
+            ]
+        );
+
+        let query = query::Query {
+            needle: Some(query::expr::Expr::try_from("due<2025-01-01 and todo")?),
+            ..Default::default()
+        };
+        let answer = query::search(&forest, &query, &query::From::Ctx)?;
+        let mut locations = Vec::new();
+        answer.each_location(|location, _meta| {
+            locations.push(format!(
+                "{}:{}",
+                location.filename.display(),
+                location.line_nr
+            ))
+        });
+        assert_eq!(locations, ["plan.org:2"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_front_matter() -> util::Result<()> {
+        let mut builder = Builder::new();
+        let forest = builder.create_forest_from_files(&[
+            (
+                "defs.md",
+                "# Defs &!:status:~status\n- &!:bug\n- &!:due:~date\n- &!:prio:~priority",
+            ),
+            (
+                "note.md",
+                "---\ntitle: Note\ntags: [bug]\nstatus: todo\ndue: 2024-10-01\nprio: a1\n---\n# Note\n- A &done",
+            ),
+            ("proj.md", "+++\ndef = \":proj:amp\"\n+++\n# Proj\n- B"),
+        ])?;
+
+        let root = forest.trees[1].root();
+        assert_eq!(
+            root.org.to_string(),
+            ":bug :status:todo :due:2024-10-01 :prio:a1"
+        );
+        // Front matter applies to the whole file
+        let node = forest.trees[1].nodes.last().unwrap();
+        assert_eq!(
+            node.ctx.to_string(),
+            ":status:done :bug :status:todo :due:2024-10-01 :prio:a1"
+        );
+
+        let root = forest.trees[2].root();
+        assert_eq!(
+            root.def.as_ref().map(|def| def.to_string()),
+            Some("!:proj:amp".into())
+        );
+        let node = forest.trees[2].nodes.last().unwrap();
+        assert_eq!(
+            node.def.as_ref().map(|def| def.to_string()),
+            Some("!:proj:amp".into())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ids() -> util::Result<()> {
+        let mut builder = Builder::new();
+        let forest = builder.create_forest_from_md(&[
+            "# Plan &#3\n- A &#10\n- B &todo &#1a\n- C &#10",
+            "- D &#7 &todo\r\n- E\r\n",
+        ])?;
+
+        let lines = |id: u32| {
+            forest
+                .ids
+                .get(&amp::Id(id))
+                .map(|ixs| ixs.iter().map(|ix| forest.location(ix)).collect::<Vec<_>>())
+        };
+        assert_eq!(lines(3), Some(vec!["0.md:1".to_string()]));
+        assert_eq!(lines(7), Some(vec!["1.md:1".to_string()]));
+        // Duplicate
+        assert_eq!(
+            lines(10),
+            Some(vec!["0.md:2".to_string(), "0.md:4".to_string()])
+        );
+        assert_eq!(forest.ids.len(), 3);
+
+        assert_eq!(
+            forest.find_id(&amp::Id(10)).map(|ix| forest.location(ix)),
+            Some("0.md:2".to_string())
+        );
+        assert!(forest.find_id(&amp::Id(1)).is_none());
+        assert_eq!(forest.next_id(), amp::Id(11));
+        assert_eq!(tree::Forest::new().next_id(), amp::Id(1));
+
+        // An Id in `&.md` is not duplicated into the Folder it describes
+        let mut md_forest = tree::Forest::new();
+        let md_path = std::path::Path::new("t2/&.md");
+        let md_tree = builder.create_tree("- &#10", format_from_path(md_path), md_path);
+        let md_ix = md_forest.add(md_tree, 1)?;
+        let mut folder = Tree::folder(std::path::Path::new("t2"));
+        folder.root_mut().links = vec![md_ix];
+        md_forest.add(folder, 0)?;
+        builder.run_passes(&mut md_forest)?;
+        let locations = md_forest.ids.get(&amp::Id(10)).map(|ixs| {
+            ixs.iter()
+                .map(|ix| md_forest.location(ix))
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(locations, Some(vec!["t2/&.md:1".to_string()]));
+
+        let tree = &forest.trees[1];
+        let scns = [
+            (0, Some("- D &#7 &todo &#11\r\n- E\r\n")),
+            (1, Some("- D &#7 &todo\r\n- E &#11\r\n")),
+            (2, None),
+        ];
+        for (line_ix, exp) in scns {
+            assert_eq!(
+                tree.append_to_line(line_ix, " &#11").as_deref(),
+                exp,
+                "{line_ix}"
+            );
+        }
+
+        // Org-mode tags must stay at the end of a heading
+        let forest = builder.create_forest_from_files(&[(
+            "plan.org",
+            "* TODO Fix parser   :bug:ui:\n* Time 10:30:\nbody :a:",
+        )])?;
+        let tree = &forest.trees[0];
+        let scns = [
+            (0, "* TODO Fix parser &#1   :bug:ui:"),
+            (1, "* Time 10:30: &#1"),
+            (2, "body :a: &#1"),
+        ];
+        for (line_ix, exp) in scns {
+            let content = tree.append_to_line(line_ix, " &#1").unwrap_or_default();
+            assert_eq!(content.lines().nth(line_ix), Some(exp), "{line_ix}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_checkbox() -> util::Result<()> {
+        let scns = [
+            // Plain checklist without `~status` definition
+            (
+                "- [ ] a\n- [x] b\n- [-] c\n- [/] d",
+                vec!["todo", "done", "cancelled", "wip"],
+            ),
+            // Resolved against the `~status` definition
+            ("# Tasks &!:status:~status\n- [ ] a", vec![":status:todo"]),
+            // Explicit Status takes precedence
+            (
+                "# Tasks &!:status:~status\n- [ ] a &done",
+                vec![":status:done"],
+            ),
+        ];
+        for (content, exp) in scns {
+            let mut builder = Builder::new();
+            let forest = builder.create_forest_from_md(&[content])?;
+
+            let mut orgs = Vec::new();
+            forest.each_node(|_tree, node| {
+                if node.checkbox.is_some() {
+                    orgs.push(node.org.to_string());
+                }
+                Ok(())
+            })?;
+            assert_eq!(orgs, exp);
+        }
+
+        // Query a plain checklist on Status
+        let mut builder = Builder::new();
+        let forest = builder.create_forest_from_md(&["- [ ] a\n- [x] b"])?;
+        let needle = amp::Path::try_from("todo")?;
+        let mut open = Vec::new();
+        forest.each_node(|_tree, node| {
+            if node.org.matches_with(&needle, &forest.env) {
+                open.push(node.is_open());
+            }
+            Ok(())
+        })?;
+        assert_eq!(open, vec![true]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rank() -> util::Result<()> {
+        let mut builder = Builder::new();
+        let forest = builder.create_forest_from_md(&["# Plan &!:scope:~scope\n- &!:risk:~risk\n- &!:effort:~effort\n- a &could\n- b &must &risk:high &effort:xs"],
+        )?;
+
+        let mut ranks = Vec::new();
+        forest.each_node(|_tree, node| {
+            if !node.org.is_empty() {
+                ranks.push((
+                    node.org.to_string(),
+                    rank::Rank::from_paths(&node.org).score,
+                ));
+            }
+            Ok(())
+        })?;
+        assert_eq!(
+            ranks,
+            vec![
+                (":scope:could".to_string(), 150),
+                (":scope:must :risk:high :effort:xs".to_string(), 370)
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_risk_effort_aliases() -> util::Result<()> {
+        let mut builder = Builder::new();
+        let forest = builder.create_forest_from_md(&[
+            "# Plan &!:risk:~risk\n- &!:effort:~effort\n- a &medium\n- b &med\n- c &m",
+        ])?;
+
+        let orgs = test_util::orgs(&forest);
+        // Both templates are present, each alias resolves to a single one
+        assert_eq!(orgs, [":risk:medium", ":effort:m", ":effort:m"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_weekday_tags() -> util::Result<()> {
+        // A Tuesday
+        let mut builder = Builder::new().with_env(amp::Env {
+            today: amp::Date::new(2024, 12, 31),
+            ..Default::default()
+        });
+        let forest = builder.create_forest_from_md(&[
+            "# Plan &!:due:~date\n- &!:sun\n- a &sun\n- b &mon\n- c &due:monday\n- d &due:mon",
+        ])?;
+
+        let orgs = test_util::orgs(&forest);
+        // Abbreviated weekdays stay plain Tags, only full names are interpreted as Date
+        assert_eq!(orgs, [":sun", ":due:2025-01-06"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregates() -> util::Result<()> {
+        let mut builder = Builder::new();
+        let mut forest = builder.create_forest_from_md(&[
+            "# Plan &!:status:~status\n- &!:est:~duration\n- &!:due:~date\n\
+            # Epic\n\
+            - A &done &est:1d\n\
+            - B &todo &est:4h &due:2024-10-10\n\
+            -- B1 &todo &est:2h &due:2024-10-05\n\
+            - C &todo",
+            "- D &done\n- E &wip &est:1h",
+        ])?;
+
+        let scns = [
+            ("# Epic", "1/4 done, 6h left, due 2024-10-05"),
+            // &spec: a parent Estimate is its own work, added to that of its children
+            ("B &todo", "0/2 done, 6h left, due 2024-10-05"),
+            ("A &done", "1/1 done"),
+            ("C &todo", "0/1 done"),
+            ("B1 &todo", "0/1 done, 2h left, due 2024-10-05"),
+        ];
+        for (content, exp) in scns {
+            let agg = test_util::find(&forest, content).map(|node| node.agg.to_string());
+            assert_eq!(agg, Some(exp.to_string()), "{content}");
+        }
+
+        // Aggregation across Tree links
+        let mut folder = Tree::folder(std::path::Path::new("folder"));
+        folder.root_mut().links = vec![0, 1];
+        let folder_ix = forest.add(folder, 0)?;
+        builder.init_aggregates(&mut forest)?;
+        assert_eq!(
+            forest.trees[folder_ix].root().agg.to_string(),
+            "2/6 done, 7h left, due 2024-10-05"
+        );
+
+        Ok(())
+    }
 }
@@ -0,0 +1,116 @@
+use crate::tree;
+
+// Line-oriented Tree for `.amp` files, where nesting follows the indentation.
+// Each non-blank line becomes a Node with a single Meta Part that excludes the indentation.
+// A tab counts as a single char of indentation: mixing tabs and spaces is not supported.
+#[derive(Default, Debug)]
+pub struct Tree {
+    pub nodes: Vec<Node>,
+}
+
+#[derive(Default, Debug)]
+pub struct Node {
+    pub line_ix: u64,
+    pub parts: Vec<Part>,
+    pub childs: Vec<usize>,
+}
+
+type Part = tree::Part;
+
+impl Tree {
+    pub fn new() -> Tree {
+        Tree::default()
+    }
+
+    pub fn init(&mut self, content: &str) {
+        self.nodes.clear();
+        let root_ix = self.append(Node::default());
+
+        // Indentation and ix of the Nodes that can still receive childs, the innermost last
+        let mut parents = Vec::<(usize, usize)>::new();
+
+        let mut offset = 0;
+        for (line_ix, line) in content.split_inclusive('\n').enumerate() {
+            let start = offset;
+            offset += line.len();
+
+            let text = line.trim_end();
+            let indent = text.len() - text.trim_start().len();
+            if indent == text.len() {
+                // Blank lines do not result in a Node
+                continue;
+            }
+
+            while parents
+                .last()
+                .is_some_and(|(parent_indent, _)| *parent_indent >= indent)
+            {
+                parents.pop();
+            }
+            let parent_ix = parents.last().map_or(root_ix, |(_, ix)| *ix);
+
+            let ix = self.append(Node {
+                line_ix: line_ix as u64,
+                parts: vec![Part::new(
+                    &(start + indent..start + text.len()),
+                    tree::Kind::Meta,
+                )],
+                childs: Vec::new(),
+            });
+            self.nodes[parent_ix].childs.push(ix);
+            parents.push((indent, ix));
+        }
+    }
+
+    fn append(&mut self, node: Node) -> usize {
+        let ix = self.nodes.len();
+        self.nodes.push(node);
+        ix
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tree::test_util;
+
+    fn print(tree: &Tree, content: &str) -> String {
+        test_util::print(
+            &tree.nodes,
+            |node| &node.childs,
+            |node, s| test_util::push_parts(&node.parts, content, s),
+        )
+    }
+
+    #[test]
+    fn test_tree() {
+        let scns = [
+            ("", "()"),
+            ("\n  \n", "()"),
+            ("a", "((a))"),
+            ("a\nb", "((a)(b))"),
+            ("a\n  b\n  c\nd", "((a(b)(c))(d))"),
+            ("a\n  b\n    c\n  d", "((a(b(c))(d)))"),
+            ("a\n    b\n  c", "((a(b)(c)))"),
+            ("a\n\n  b  \r\n", "((a(b)))"),
+            ("\ta\n\t\tb\nc", "((a(b))(c))"),
+            ("  a\nb", "((a)(b))"),
+        ];
+
+        for (content, exp) in scns {
+            let mut tree = Tree::new();
+            tree.init(content);
+            assert_eq!(print(&tree, content), exp, "{content:?}");
+        }
+
+        let mut tree = Tree::new();
+        tree.init("a\n\n  b");
+        let line_ixs = tree
+            .nodes
+            .iter()
+            .map(|node| node.line_ix)
+            .collect::<Vec<_>>();
+        assert_eq!(line_ixs, [0, 0, 2]);
+    }
+}
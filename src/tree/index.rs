@@ -0,0 +1,215 @@
+use crate::{amp, tree};
+use std::collections;
+
+type Key = Vec<String>;
+type Ixs = collections::BTreeSet<tree::NodeIx>;
+
+// Inverted index from each Path in Node.org and Node.ctx, and its prefixes, to the Nodes that contain them.
+// Eg, `:team:owner=alice` is indexed under `team` and `team:owner`.
+// Only Parts with a textual identity can be looked up: Tags, KeyValue keys, Status names and Ids.
+// Lookups return a superset of the matching Nodes, callers still need to check the actual match.
+#[derive(Default, Debug)]
+pub struct Index {
+    org: Map,
+    ctx: Map,
+}
+
+#[derive(Default, Debug)]
+struct Map {
+    paths: collections::BTreeMap<Key, Ixs>,
+    // From the last Part of each key in `paths`, for relative lookups
+    parts: collections::BTreeMap<String, Ixs>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Scope {
+    Org,
+    Ctx,
+}
+
+impl Index {
+    pub fn new(forest: &tree::Forest) -> Index {
+        let mut index = Index::default();
+        for tree in &forest.trees {
+            for node in &tree.nodes {
+                let ix = node.ix();
+                for (paths, map) in [(&node.org, &mut index.org), (&node.ctx, &mut index.ctx)] {
+                    for path in &paths.data {
+                        let mut key = Key::new();
+                        for part in &path.parts {
+                            // Typed Parts are represented by an empty key that never matches a needle
+                            let part_key = stored_key(part).unwrap_or_default();
+                            map.parts.entry(part_key.clone()).or_default().insert(ix);
+                            key.push(part_key);
+                            map.paths.entry(key.clone()).or_default().insert(ix);
+                        }
+                    }
+                }
+            }
+        }
+        index
+    }
+
+    fn map(&self, scope: Scope) -> &Map {
+        match scope {
+            Scope::Org => &self.org,
+            Scope::Ctx => &self.ctx,
+        }
+    }
+
+    // Candidate Nodes that might match with `needle`, None when the index cannot answer this
+    pub fn lookup(&self, scope: Scope, needle: &amp::Path) -> Option<Ixs> {
+        let map = self.map(scope);
+        if needle.is_absolute {
+            // The leading Parts of an absolute needle must match the leading Parts of the Path
+            let key = needle.parts.iter().map_while(needle_key).collect::<Key>();
+            if key.is_empty() {
+                return None;
+            }
+            Some(map.paths.get(&key).cloned().unwrap_or_default())
+        } else {
+            // Any Part of a relative needle must occur somewhere in the Path
+            let part_key = needle.parts.iter().find_map(needle_key)?;
+            Some(self.lookup_part(scope, &part_key))
+        }
+    }
+
+    // Nodes with a Path that contains the Tag, KeyValue key or Status name `text`
+    pub fn lookup_text(&self, scope: Scope, text: &str) -> Ixs {
+        self.lookup_part(scope, &normalize(text))
+    }
+
+    fn lookup_part(&self, scope: Scope, part_key: &str) -> Ixs {
+        self.map(scope)
+            .parts
+            .get(part_key)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+fn normalize(s: &str) -> String {
+    if s.is_ascii() {
+        s.to_ascii_lowercase()
+    } else {
+        amp::normalize(s)
+    }
+}
+
+fn stored_key(part: &amp::Part) -> Option<String> {
+    match part {
+        amp::Part::Tag(tag) => Some(normalize(&tag.text)),
+        amp::Part::KeyValue(kv) => Some(normalize(&kv.0)),
+        amp::Part::Status(status) => Some(normalize(&status.name)),
+        amp::Part::Id(id) => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+// A needle Tag that can be interpreted as a typed value can match with Parts that are not indexed
+fn needle_key(part: &amp::Part) -> Option<String> {
+    match part {
+        amp::Part::Tag(tag) => {
+            let text = tag.text.as_str();
+            let is_typed = amp::Date::is_date(text)
+                || amp::Duration::try_from(text).is_ok()
+                || amp::Estimate::try_from(text).is_ok()
+                || amp::Prio::try_from(text).is_ok()
+                || amp::Scope::try_from(text).is_ok()
+                || amp::Risk::try_from(text).is_ok()
+                || amp::Effort::try_from(text).is_ok();
+            (!is_typed).then(|| normalize(text))
+        }
+        amp::Part::Gap => None,
+        part => stored_key(part),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query::{self, expr::Expr};
+    use crate::{tree::builder, util};
+
+    #[test]
+    fn test_index() -> util::Result<()> {
+        let mut builder = builder::Builder::new();
+        let forest = builder.create_forest_from_md(&[
+            "# Plan &!:status:~status\n- &!:bug\n- &!:feature\n- &!:team:owner\n\
+            - &!:due:~date\n\
+            ## Parser &bug\n\
+            - A &todo &team:owner=alice\n\
+            - B &done &due:2024-11-01\n\
+            ## Lexer &feature\n\
+            - C &todo &bug &team:owner=bob &#3",
+            "# Other\n- D\n- E &feature",
+        ])?;
+        let node_count: usize = forest.trees.iter().map(|tree| tree.nodes.len()).sum();
+
+        let scns = [
+            ("bug", query::From::Ctx, true),
+            ("bug", query::From::Org, true),
+            (":team", query::From::Ctx, true),
+            (":team:owner", query::From::Org, true),
+            ("owner=bob", query::From::Ctx, true),
+            ("todo", query::From::Ctx, true),
+            ("#3", query::From::Org, true),
+            ("due<2025-01-01", query::From::Ctx, true),
+            ("due>=2024-11-01", query::From::Org, true),
+            ("owner=alice", query::From::Org, true),
+            ("team:owner=bob", query::From::Ctx, true),
+            ("unknown", query::From::Ctx, true),
+            ("bug or feature", query::From::Ctx, true),
+            ("bug and not done", query::From::Ctx, true),
+            ("org(feature) and ctx(bug)", query::From::Ctx, true),
+            ("2024-11-01", query::From::Ctx, false),
+            ("not bug", query::From::Ctx, false),
+            ("bug or ~open", query::From::Ctx, false),
+            ("fuzzy(bgu)", query::From::Ctx, false),
+            ("bug", query::From::Def, false),
+        ];
+
+        for (s, from, exp_is_indexed) in scns {
+            let query = query::Query {
+                needle: Some(Expr::try_from(s)?),
+                ..Default::default()
+            };
+            let candidates = query.candidates(&forest.index, &from);
+            assert_eq!(candidates.is_some(), exp_is_indexed, "{s}");
+
+            // The candidates must contain all matches
+            let mut matches = Vec::new();
+            forest.dfs(|_tree, node| {
+                if query.matches(node, &from, &forest.env) {
+                    matches.push(node.ix());
+                }
+                Ok(())
+            })?;
+            if let Some(candidates) = candidates {
+                assert!(candidates.len() < node_count, "{s}");
+                for ix in &matches {
+                    assert!(candidates.contains(ix), "{s} {ix:?}");
+                }
+            }
+
+            // Searching with the index finds the same Nodes as checking each Node
+            let answer = query::search(&forest, &query, &from)?;
+            let mut line_nrs = Vec::new();
+            answer.each_location(|location, _meta| {
+                line_nrs.push((location.filename.clone(), location.line_nr))
+            });
+            line_nrs.sort();
+            let mut exp_line_nrs = matches
+                .iter()
+                .filter_map(|ix| {
+                    let tree = forest.trees.get(ix.tree_ix)?;
+                    let node = forest.node(ix)?;
+                    Some((tree.filename.clone(), node.line_ix.unwrap_or(0) + 1))
+                })
+                .collect::<Vec<_>>();
+            exp_line_nrs.sort();
+            assert_eq!(line_nrs, exp_line_nrs, "{s}");
+        }
+
+        Ok(())
+    }
+}
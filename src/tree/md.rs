@@ -4,14 +4,27 @@ use crate::{lex, tree};
 pub struct Tree {
     // Tree data itself
     pub nodes: Vec<Node>,
+    // Key and values from the YAML (`---`) or TOML (`+++`) front matter, keys are lowercase
+    pub front_matter: FrontMatter,
 
     // Used during Tree.init()
     headers: Vec<usize>,
     bullets: Vec<usize>,
     codeblock: Option<usize>,
     formulablock: Option<usize>,
+    indentedcode: Option<usize>,
     state: State,
     prev_state: State,
+    // Indentation of the list items that determine the nesting of indented and numbered items: (marker, content)
+    list_indents: Vec<(usize, usize)>,
+    // Line bookkeeping: indentation of the current line and info about the previous line
+    indent: usize,
+    line_has_text: bool,
+    prev_blank: bool,
+    paragraph: Option<usize>,
+    prev_paragraph: Option<usize>, // Candidate for a setext header underline
+    quote: Option<usize>,
+    prev_quote: Option<usize>, // Block quote that continues on this line
 }
 
 #[derive(Default, Debug)]
@@ -19,9 +32,12 @@ pub struct Node {
     pub line_ix: u64,
     pub parts: Vec<Part>,
     pub childs: Vec<usize>,
+    // Status name for a task-list checkbox like `- [x]`
+    pub checkbox: Option<&'static str>,
 }
 
 type Part = tree::Part;
+pub type FrontMatter = Vec<(String, Vec<String>)>;
 
 #[derive(Debug, Clone)]
 enum State {
@@ -30,6 +46,7 @@ enum State {
     Bullet,
     Code,
     CodeBlock,
+    IndentedCode,
     Formula,
     FormulaBlock,
 }
@@ -46,27 +63,68 @@ impl Tree {
         Tree::default()
     }
 
-    // &todo: detect blocks indented with a few spaces (how many?) as a CodeBlock
-    pub fn init(&mut self, tokens: &[Token]) {
+    pub fn init(&mut self, tokens: &[Token], content: &str) {
         self.nodes.clear();
         self.headers.clear();
         self.bullets.clear();
+        self.front_matter.clear();
+        self.indentedcode = None;
+        self.list_indents.clear();
+        self.indent = 0;
+        self.line_has_text = false;
+        self.prev_blank = true;
+        self.paragraph = None;
+        self.prev_paragraph = None;
+        self.quote = None;
+        self.prev_quote = None;
 
         let root_ix = self.append(Node::default());
         self.headers.push(root_ix);
 
+        // Tokens within the front matter are not interpreted as Markdown
+        let mut body_start = 0;
+        if let Some((front_matter, end)) = front_matter(content) {
+            self.front_matter = front_matter;
+            body_start = end;
+        }
+
         self.state = State::Idle;
-        for token in tokens {
+        for token in tokens
+            .iter()
+            .skip_while(|token| token.range.start < body_start)
+        {
+            if token.kind == lex::Kind::Newline {
+                self.prev_blank = !self.line_has_text;
+                self.line_has_text = false;
+                self.indent = 0;
+                self.prev_paragraph = self.paragraph.take();
+                self.prev_quote = self.quote.take();
+            } else if indentation(token, content).is_none() {
+                self.line_has_text = true;
+            }
+
             match self.state {
                 State::Idle => {
                     if false {
-                    } else if self.handle_newline(token) {
+                    } else if self.handle_newline(token) || self.handle_indentation(token, content)
+                    {
                     } else if self.handle_backtick(token) {
                     } else if self.handle_dollar(token) {
                     } else {
                         match token.kind {
+                            lex::Kind::Equal | lex::Kind::Dash
+                                if self.prev_paragraph.is_some()
+                                    && is_rest_blank(token, content) =>
+                            {
+                                // &spec: a `===` or `---` line below a paragraph line turns it into a setext header
+                                let level = if token.kind == lex::Kind::Equal { 1 } else { 2 };
+                                self.promote_to_header(token, level);
+
+                                self.state = State::Header;
+                            }
                             lex::Kind::Hash => {
                                 self.bullets.clear();
+                                self.list_indents.clear();
 
                                 let level = token.range.len();
                                 assert!(level > 0);
@@ -87,21 +145,50 @@ impl Tree {
                                 self.state = State::Header;
                             }
                             lex::Kind::Dash | lex::Kind::Star => {
-                                let level = token.range.len();
-                                assert!(level > 0);
-                                // Drop all bullets that are nested deeper
-                                while self.bullets.len() > level {
-                                    self.bullets.pop();
-                                }
-                                // Create missing bullets
-                                while self.bullets.len() <= level {
-                                    let node = Node::new(token.line_ix);
-                                    let ix = self.append(node);
-                                    self.bullet().childs.push(ix);
-                                    self.bullets.push(ix);
+                                let level = if self.indent == 0 {
+                                    // &spec: unindented bullets are nested via their number of `-` or `*`
+                                    let level = token.range.len();
+                                    // The content indent of the implicit outer items is never reached
+                                    self.list_indents = vec![(0, usize::MAX); level];
+                                    level
+                                } else {
+                                    self.list_level()
+                                };
+                                self.add_bullet(token, level, content);
+
+                                self.state = State::Bullet;
+                            }
+                            lex::Kind::Text if is_numbered(token, content) => {
+                                // &spec: numbered items `1.` or `1)` are nested via their indentation
+                                let level = self.list_level();
+                                self.add_bullet(token, level, content);
+
+                                self.state = State::Bullet;
+                            }
+                            lex::Kind::Text if content[token.range.clone()].starts_with('>') => {
+                                // &spec: consecutive `>` lines form a single block quote
+                                let text = &content[token.range.clone()];
+                                let marker_len = text.len() - text.trim_start_matches('>').len();
+                                let marker = token.range.start..token.range.start + marker_len;
+                                let rest = marker.end..token.range.end;
+
+                                let ix = match self.prev_quote {
+                                    Some(ix) => ix,
+                                    None => {
+                                        let ix = self.append(Node::new(token.line_ix));
+                                        self.header().childs.push(ix);
+                                        self.bullets.clear();
+                                        self.bullets.push(ix);
+                                        self.list_indents.clear();
+                                        ix
+                                    }
+                                };
+                                let node = &mut self.nodes[ix];
+                                node.parts.push(Part::new(&marker, tree::Kind::Data));
+                                if !rest.is_empty() {
+                                    node.parts.push(Part::new(&rest, tree::Kind::Meta));
                                 }
-                                let bullet = self.bullet();
-                                bullet.parts.push(Part::new(&token.range, tree::Kind::Data));
+                                self.quote = Some(ix);
 
                                 self.state = State::Bullet;
                             }
@@ -109,9 +196,27 @@ impl Tree {
                                 let mut node = Node::new(token.line_ix);
                                 node.parts.push(Part::new(&token.range, tree::Kind::Meta));
                                 let ix = self.append(node);
-                                self.header().childs.push(ix);
-                                self.bullets.clear();
-                                self.bullets.push(ix);
+                                // &spec: text indented up to the content of a list item belongs to that item
+                                let level = self
+                                    .list_indents
+                                    .iter()
+                                    .rposition(|(_, content_indent)| *content_indent <= self.indent)
+                                    .filter(|_| self.indent > 0);
+                                match level {
+                                    Some(level) => {
+                                        self.list_indents.truncate(level + 1);
+                                        self.bullets.truncate(level + 2);
+                                        self.bullet().childs.push(ix);
+                                        self.bullets.push(ix);
+                                    }
+                                    None => {
+                                        self.header().childs.push(ix);
+                                        self.bullets.clear();
+                                        self.bullets.push(ix);
+                                        self.list_indents.clear();
+                                        self.paragraph = Some(ix);
+                                    }
+                                }
 
                                 self.state = State::Bullet;
                             }
@@ -159,6 +264,12 @@ impl Tree {
                         self.state = State::Idle;
                     }
                 }
+                State::IndentedCode => {
+                    let node = &mut self.nodes[self.indentedcode.unwrap()];
+                    node.parts.push(Part::new(&token.range, tree::Kind::Data));
+
+                    self.handle_newline(token);
+                }
                 State::Formula => {
                     if false {
                     } else if self.handle_newline(token) {
@@ -193,6 +304,58 @@ impl Tree {
         }
     }
 
+    // Leading whitespace determines the indentation of the line
+    fn handle_indentation(&mut self, token: &Token, content: &str) -> bool {
+        // The lexer keeps leading tabs and text in a single Token
+        let text = content.get(token.range.clone()).unwrap_or("");
+        let tab_count = text.len() - text.trim_start_matches('\t').len();
+        let (width, has_text) = match token.kind {
+            lex::Kind::Space => (token.range.len(), false),
+            lex::Kind::Text if tab_count > 0 => (4 * tab_count, tab_count < text.len()),
+            _ => {
+                // A line that starts with text ends an indented code block
+                self.indentedcode = None;
+                return false;
+            }
+        };
+        self.indent += width;
+
+        // &spec: lines indented with at least 4 spaces or a tab after a blank line form a code block,
+        // within a list item this indentation is relative to the content of the item
+        let content_indent = self
+            .list_indents
+            .iter()
+            .rev()
+            .map(|(_, content_indent)| *content_indent)
+            .find(|content_indent| *content_indent <= self.indent)
+            .unwrap_or(0);
+        let is_code = self.indent >= content_indent + 4
+            && (self.indentedcode.is_some()
+                || (self.prev_blank && (has_text || !is_rest_blank(token, content))));
+        if is_code {
+            let ix = match self.indentedcode {
+                Some(ix) => ix,
+                None => {
+                    let ix = self.append(Node::new(token.line_ix));
+                    self.bullet().childs.push(ix);
+                    self.bullets.push(ix);
+                    ix
+                }
+            };
+            self.nodes[ix]
+                .parts
+                .push(Part::new(&token.range, tree::Kind::Data));
+            self.indentedcode = Some(ix);
+            self.state = State::IndentedCode;
+            true
+        } else if has_text {
+            self.indentedcode = None;
+            false
+        } else {
+            true
+        }
+    }
+
     fn handle_backtick(&mut self, token: &Token) -> bool {
         if token.kind == lex::Kind::Backtick {
             let level = token.range.len();
@@ -284,6 +447,71 @@ impl Tree {
         os.push_str(")");
     }
 
+    // Level for an item at the current indentation, deeper than the items with less indentation
+    fn list_level(&mut self) -> usize {
+        while self
+            .list_indents
+            .last()
+            .is_some_and(|(indent, _)| *indent >= self.indent)
+        {
+            self.list_indents.pop();
+        }
+        // The content indent is set by add_bullet
+        self.list_indents.push((self.indent, self.indent));
+        self.list_indents.len()
+    }
+
+    fn add_bullet(&mut self, token: &Token, level: usize, content: &str) {
+        assert!(level > 0);
+        // Drop all bullets that are nested deeper
+        while self.bullets.len() > level {
+            self.bullets.pop();
+        }
+        // Create missing bullets
+        while self.bullets.len() <= level {
+            let node = Node::new(token.line_ix);
+            let ix = self.append(node);
+            self.bullet().childs.push(ix);
+            self.bullets.push(ix);
+        }
+        // &spec: the content of a list item starts after its marker and 1 to 4 spaces
+        let rest = content.get(token.range.end..).unwrap_or("");
+        let spaces = rest.len() - rest.trim_start_matches(' ').len();
+        let spaces = if (1..=4).contains(&spaces) { spaces } else { 1 };
+        if let Some(indents) = self.list_indents.last_mut() {
+            indents.1 = self.indent + token.range.len() + spaces;
+        }
+
+        let bullet = self.bullet();
+        bullet.parts.push(Part::new(&token.range, tree::Kind::Data));
+        bullet.checkbox = content.get(token.range.end..).and_then(checkbox_status);
+    }
+
+    // Moves the paragraph from the previous line to the headers, with `token` as underline
+    fn promote_to_header(&mut self, token: &Token, level: usize) {
+        let Some(ix) = self.prev_paragraph.take() else {
+            return;
+        };
+        self.header().childs.retain(|child| *child != ix);
+        self.bullets.clear();
+        self.list_indents.clear();
+
+        // Drop all headers that are nested deeper
+        while self.headers.len() > level {
+            self.headers.pop();
+        }
+        // Missing levels refer to the enclosing header: creating them now would give a parent with a higher ix
+        while self.headers.len() < level {
+            self.headers.push(*self.headers.last().unwrap());
+        }
+        self.header().childs.push(ix);
+        self.headers.push(ix);
+
+        self.nodes[ix]
+            .parts
+            .push(Part::new(&token.range, tree::Kind::Data));
+    }
+
     fn header(&mut self) -> &mut Node {
         let ix = self.headers.last().unwrap();
         &mut self.nodes[*ix]
@@ -316,6 +544,117 @@ impl Node {
 
 type Range = std::ops::Range<usize>;
 
+// Width of a whitespace Token, a tab counts as 4 spaces
+fn indentation(token: &Token, content: &str) -> Option<usize> {
+    match token.kind {
+        lex::Kind::Space => Some(token.range.len()),
+        lex::Kind::Text => {
+            let text = content.get(token.range.clone())?;
+            text.chars().all(|ch| ch == '\t').then(|| 4 * text.len())
+        }
+        _ => None,
+    }
+}
+
+// Only whitespace follows `token` on its line
+fn is_rest_blank(token: &Token, content: &str) -> bool {
+    content
+        .get(token.range.end..)
+        .and_then(|rest| rest.split('\n').next())
+        .is_none_or(|rest| rest.trim().is_empty())
+}
+
+// Numbered list marker like `1.` or `12)`, followed by whitespace
+fn is_numbered(token: &Token, content: &str) -> bool {
+    let Some(text) = content.get(token.range.clone()) else {
+        return false;
+    };
+    let Some(digits) = text.strip_suffix(['.', ')']) else {
+        return false;
+    };
+    let is_marker = (1..=9).contains(&digits.len()) && digits.chars().all(|ch| ch.is_ascii_digit());
+    let is_followed_by_space = content[token.range.end..]
+        .chars()
+        .next()
+        .is_none_or(char::is_whitespace);
+    is_marker && is_followed_by_space
+}
+
+// &spec: front matter starts on the first line with `---` (YAML) or `+++` (TOML) and ends with the same delimiter
+// - `key: value` or `key = value`, where value can be a `[a, b]` list
+// - YAML lists can also be given as `- a` lines below `key:`
+// Returns the entries and the offset where the Markdown body starts
+fn front_matter(content: &str) -> Option<(FrontMatter, usize)> {
+    let mut lines = content.split_inclusive('\n');
+    let delim = lines.next()?.trim_end();
+    let sep = match delim {
+        "---" => ':',
+        "+++" => '=',
+        _ => return None,
+    };
+
+    let mut entries = FrontMatter::new();
+    let mut offset = content.split_inclusive('\n').next()?.len();
+    for line in lines {
+        offset += line.len();
+        let text = line.trim_end();
+        if text == delim {
+            return Some((entries, offset));
+        }
+
+        if let Some(item) = text.trim_start().strip_prefix("- ") {
+            // Continuation of a YAML list
+            if let Some((_, values)) = entries.last_mut() {
+                values.extend(unquote(item));
+            }
+        } else if let Some((key, value)) = text.split_once(sep) {
+            let value = value.trim();
+            let values = match value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+                Some(list) => list.split(',').filter_map(unquote).collect(),
+                None => unquote(value).into_iter().collect(),
+            };
+            entries.push((key.trim().to_lowercase(), values));
+        }
+    }
+
+    // Without closing delimiter, this is no front matter
+    None
+}
+
+fn unquote(value: &str) -> Option<String> {
+    let value = value.trim();
+    let value = ['"', '\'']
+        .iter()
+        .find_map(|&quote| {
+            value
+                .strip_prefix(quote)
+                .and_then(|v| v.strip_suffix(quote))
+        })
+        .unwrap_or(value);
+    (!value.is_empty()).then(|| value.to_owned())
+}
+
+// &spec: GitHub-style task-list checkboxes directly after a bullet are interpreted as Status
+// - `[ ]`: todo
+// - `[x]`: done
+// - `[-]`: cancelled
+// - `[/]`: wip
+fn checkbox_status(rest: &str) -> Option<&'static str> {
+    let rest = rest.strip_prefix(' ')?;
+    let status = match rest.get(0..3)? {
+        "[ ]" => "todo",
+        "[x]" | "[X]" => "done",
+        "[-]" => "cancelled",
+        "[/]" => "wip",
+        _ => return None,
+    };
+    // The checkbox should be followed by whitespace
+    match rest[3..].chars().next() {
+        None | Some(' ' | '\t' | '\n' | '\r') => Some(status),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -355,9 +694,133 @@ mod tests {
             lexer.tokenize(content);
 
             let mut tree = Tree::default();
-            tree.init(&lexer.tokens);
+            tree.init(&lexer.tokens, content);
 
             assert_eq!(&tree.print(content), exp);
         }
     }
+
+    #[test]
+    fn test_blocks() {
+        let scns = [
+            // Indented code
+            ("    code &todo", "((D    DcodeD D&Dtodo))"),
+            (
+                "L1\n\n    code\n\n\tmore\nL2",
+                "((ML1(D    DcodeD\nD\tmoreD\n))(ML2))",
+            ),
+            ("L1\n    continued", "((ML1)(Mcontinued))"),
+            ("- a\n\n    text", "(((D-M Ma(Mtext))))"),
+            ("- a\n\n      code", "(((D-M Ma(D      Dcode))))"),
+            (
+                "- a &todo\n\n    - b &todo\n\n- c &todo",
+                "(((D-M MaM M&Mtodo(D-M MbM M&Mtodo))(D-M McM M&Mtodo)))",
+            ),
+            // Setext headers
+            ("Title\n=====\nL1", "((MTitleD=====(ML1)))"),
+            ("# T1\nT2\n---\n- a", "((D#M MT1(MT2D---((D-M Ma)))))"),
+            ("L1\n\n---", "((ML1(((D---)))))"),
+            ("L1\n- a", "((ML1(D-M Ma)))"),
+            // Numbered lists
+            ("1. a\n2. b", "(((D1.M Ma)(D2.M Mb)))"),
+            (
+                "1. a\n   1) b\n   - c\n2. d",
+                "(((D1.M Ma(D1)M Mb)(D-M Mc))(D2.M Md)))",
+            ),
+            ("1.5 apples", "((M1.5M Mapples))"),
+            ("- a\n  - b", "(((D-M Ma(D-M Mb))))"),
+            // Block quotes
+            ("> q1 &todo\n> q2\nL1", "((D>M Mq1M M&MtodoD>M Mq2)(ML1))"),
+            (">q1\n\n> q2", "((D>Mq1)(D>M Mq2))"),
+        ];
+        let mut lexer = lex::Lexer::new();
+
+        for (content, exp) in scns {
+            lexer.tokenize(content);
+
+            let mut tree = Tree::default();
+            tree.init(&lexer.tokens, content);
+
+            assert_eq!(tree.print(content), exp, "{content:?}");
+        }
+    }
+
+    #[test]
+    fn test_front_matter() {
+        let scns = [
+            (
+                "---\ntags: [a, \"b\"]\nstatus: done\n---\n# T",
+                vec!["tags=a,b", "status=done"],
+                Some("((D#M MT))"),
+            ),
+            (
+                "+++\ntags = ['a']\ndue = \"2024-10-01\"\n+++\n- x",
+                vec!["tags=a", "due=2024-10-01"],
+                Some("(((D-M Mx)))"),
+            ),
+            (
+                "---\nTags:\n  - a\n  - b c\nprio: \n---\n",
+                vec!["tags=a,b c", "prio="],
+                Some("()"),
+            ),
+            (
+                "---\r\ndef: :proj:amp\r\n---\r\nL1",
+                vec!["def=:proj:amp"],
+                Some("((ML1))"),
+            ),
+            // Not at the start or not closed
+            ("\n---\ntags: a\n---", vec![], None),
+            ("---\ntags: a\n# T", vec![], None),
+        ];
+        let mut lexer = lex::Lexer::new();
+
+        for (content, exp_entries, exp_tree) in scns {
+            lexer.tokenize(content);
+
+            let mut tree = Tree::default();
+            tree.init(&lexer.tokens, content);
+
+            let entries = tree
+                .front_matter
+                .iter()
+                .map(|(key, values)| format!("{key}={}", values.join(",")))
+                .collect::<Vec<_>>();
+            assert_eq!(entries, exp_entries, "{content:?}");
+            if let Some(exp_tree) = exp_tree {
+                assert_eq!(tree.print(content), exp_tree, "{content:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_checkbox() {
+        let scns = [
+            ("- [ ] a", Some("todo")),
+            ("- [x] a", Some("done")),
+            ("* [X] a", Some("done")),
+            ("- [-] a", Some("cancelled")),
+            ("- [/] a", Some("wip")),
+            ("- [ ]", Some("todo")),
+            ("- [x]a", None),
+            ("- [y] a", None),
+            ("- a [x]", None),
+            ("[x] a", None),
+            ("# [x] a", None),
+        ];
+        let mut lexer = lex::Lexer::new();
+
+        for (content, exp) in scns {
+            lexer.tokenize(content);
+
+            let mut tree = Tree::default();
+            tree.init(&lexer.tokens, content);
+
+            let checkboxes = tree
+                .nodes
+                .iter()
+                .filter_map(|node| node.checkbox)
+                .collect::<Vec<_>>();
+            assert_eq!(checkboxes, exp.into_iter().collect::<Vec<_>>(), "{content}");
+        }
+    }
 }
@@ -0,0 +1,350 @@
+use crate::{fail, tree, util};
+
+// Reader for FreeMind/Freeplane mind-maps (`.mm`): each `<node TEXT="...">` becomes a Node, nested as in the map.
+// The XML cannot be used as Tree.content since TEXT attributes are entity-encoded, eg `&amp;todo`.
+// Tree.content holds the decoded TEXT of each node on the line where its `<node` starts instead,
+// keeping line numbers aligned with the original file.
+// &todo: support RICHCONTENT nodes that store their text as HTML
+#[derive(Default, Debug)]
+pub struct Tree {
+    // Tree data itself
+    pub nodes: Vec<Node>,
+    pub content: String,
+}
+
+#[derive(Default, Debug)]
+pub struct Node {
+    pub line_ix: u64,
+    pub parts: Vec<Part>,
+    pub childs: Vec<usize>,
+}
+
+type Part = tree::Part;
+
+impl Tree {
+    pub fn new() -> Tree {
+        Tree::default()
+    }
+
+    // On failure, the Nodes that were read before the error remain available
+    pub fn init(&mut self, xml: &str) -> util::Result<()> {
+        self.nodes.clear();
+        self.content.clear();
+
+        let root_ix = self.append(Node::default());
+
+        // Decoded TEXT per node, in document order
+        let mut texts = Vec::<(usize, String)>::new();
+        let res = self.read_nodes(xml, root_ix, &mut texts);
+
+        // Lay out the texts line per line, separated with a space when several nodes start on the same line
+        let mut texts = texts.into_iter().peekable();
+        for (line_ix, _) in xml.split('\n').enumerate() {
+            if line_ix > 0 {
+                self.content.push('\n');
+            }
+            let mut is_first = true;
+            while let Some((ix, text)) =
+                texts.next_if(|(ix, _)| self.nodes[*ix].line_ix == line_ix as u64)
+            {
+                if !is_first {
+                    self.content.push(' ');
+                }
+                is_first = false;
+                if !text.is_empty() {
+                    let start = self.content.len();
+                    self.content.push_str(&text);
+                    let end = self.content.len();
+                    self.nodes[ix]
+                        .parts
+                        .push(Part::new(&(start..end), tree::Kind::Meta));
+                }
+            }
+        }
+
+        res
+    }
+
+    fn read_nodes(
+        &mut self,
+        xml: &str,
+        root_ix: usize,
+        texts: &mut Vec<(usize, String)>,
+    ) -> util::Result<()> {
+        // Open `<node>` elements, the innermost last
+        let mut stack = vec![root_ix];
+
+        let mut line_ix = 0;
+        let mut line_pos = 0;
+        let mut pos = 0;
+        while let Some(offset) = xml[pos..].find('<') {
+            let start = pos + offset;
+            line_ix += xml[line_pos..start].matches('\n').count() as u64;
+            line_pos = start;
+
+            let markup = &xml[start..];
+            let len = if markup.starts_with("<!--") {
+                end_of(markup, "-->", line_ix)?
+            } else if markup.starts_with("<?") {
+                end_of(markup, "?>", line_ix)?
+            } else if markup.starts_with("<!") {
+                end_of(markup, ">", line_ix)?
+            } else if let Some(rest) = markup.strip_prefix("</") {
+                let len = end_of(markup, ">", line_ix)?;
+                if rest[..len - 3].trim() == "node" {
+                    if stack.len() <= 1 {
+                        fail!("Found unmatched '</node>' on line {}", line_ix + 1);
+                    }
+                    stack.pop();
+                }
+                len
+            } else {
+                let element = Element::parse(markup, line_ix)?;
+                if element.name == "node" {
+                    let ix = self.append(Node {
+                        line_ix,
+                        ..Default::default()
+                    });
+                    let text = element
+                        .attrs
+                        .iter()
+                        .find(|(name, _)| *name == "TEXT")
+                        .map(|(_, value)| decode(value))
+                        .unwrap_or_default();
+                    texts.push((ix, text));
+
+                    let parent_ix = *stack.last().unwrap();
+                    self.nodes[parent_ix].childs.push(ix);
+                    if !element.is_empty {
+                        stack.push(ix);
+                    }
+                }
+                element.len
+            };
+            pos = start + len;
+        }
+
+        if stack.len() > 1 {
+            let ix = *stack.last().unwrap();
+            fail!(
+                "Found unclosed '<node>' from line {}",
+                self.nodes[ix].line_ix + 1
+            );
+        }
+
+        Ok(())
+    }
+
+    fn append(&mut self, node: Node) -> usize {
+        let ix = self.nodes.len();
+        self.nodes.push(node);
+        ix
+    }
+}
+
+// Start tag, eg `<node ID="1" TEXT="Roadmap">`
+struct Element<'a> {
+    name: &'a str,
+    attrs: Vec<(&'a str, &'a str)>,
+    is_empty: bool, // Self-closing `<node/>`
+    len: usize,
+}
+
+impl<'a> Element<'a> {
+    fn parse(markup: &'a str, line_ix: u64) -> util::Result<Element<'a>> {
+        let is_name_end = |ch: char| ch.is_whitespace() || ch == '/' || ch == '>';
+
+        let mut rest = &markup[1..];
+        let name_len = rest.find(is_name_end).unwrap_or(rest.len());
+        let name = &rest[..name_len];
+        rest = &rest[name_len..];
+
+        let mut attrs = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            let (is_empty, tail) = if let Some(tail) = rest.strip_prefix("/>") {
+                (true, tail)
+            } else if let Some(tail) = rest.strip_prefix('>') {
+                (false, tail)
+            } else {
+                let Some((attr_name, tail)) = rest.split_once('=') else {
+                    fail!("Found unterminated '<{}' on line {}", name, line_ix + 1);
+                };
+                let tail = tail.trim_start();
+                let Some(quote) = tail.chars().next().filter(|ch| *ch == '"' || *ch == '\'') else {
+                    fail!(
+                        "Expected quoted value for '{}' on line {}",
+                        attr_name.trim(),
+                        line_ix + 1
+                    );
+                };
+                let Some((value, tail)) = tail[1..].split_once(quote) else {
+                    fail!(
+                        "Found unterminated value for '{}' on line {}",
+                        attr_name.trim(),
+                        line_ix + 1
+                    );
+                };
+                attrs.push((attr_name.trim(), value));
+                rest = tail;
+                continue;
+            };
+            return Ok(Element {
+                name,
+                attrs,
+                is_empty,
+                len: markup.len() - tail.len(),
+            });
+        }
+    }
+}
+
+// Length of `markup` up to and including `end`
+fn end_of(markup: &str, end: &str, line_ix: u64) -> util::Result<usize> {
+    match markup.find(end) {
+        Some(ix) => Ok(ix + end.len()),
+        None => fail!("Expected '{}' for markup on line {}", end, line_ix + 1),
+    }
+}
+
+// Resolves XML entities and replaces line breaks with spaces to keep each node on a single line
+fn decode(value: &str) -> String {
+    let mut s = String::new();
+    let mut rest = value;
+    while let Some(ix) = rest.find('&') {
+        s.push_str(&rest[..ix]);
+        rest = &rest[ix..];
+
+        let entity = rest[1..].split_once(';').map(|(entity, _)| entity);
+        let ch = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => {
+                let code = if let Some(hex) = entity.strip_prefix("#x") {
+                    u32::from_str_radix(hex, 16).ok()
+                } else if let Some(dec) = entity.strip_prefix('#') {
+                    dec.parse().ok()
+                } else {
+                    None
+                };
+                code.and_then(char::from_u32)
+            }
+        });
+        match (entity, ch) {
+            (Some(entity), Some(ch)) => {
+                s.push(ch);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                // Not a valid entity, keep the `&` as is
+                s.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    s.push_str(rest);
+
+    s.chars()
+        .map(|ch| {
+            if ch == '\n' || ch == '\r' || ch == '\t' {
+                ' '
+            } else {
+                ch
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tree::test_util;
+
+    fn print(tree: &Tree) -> String {
+        test_util::print(
+            &tree.nodes,
+            |node| &node.childs,
+            |node, s| test_util::push_parts(&node.parts, &tree.content, s),
+        )
+    }
+
+    #[test]
+    fn test_tree() -> util::Result<()> {
+        let scns = [
+            ("", "()"),
+            ("<map/>", "()"),
+            (r#"<map><node TEXT="Root"/></map>"#, "((Root))"),
+            (
+                "<?xml version=\"1.0\"?>\n<map version=\"1.0.1\">\n<!-- <node TEXT=\"x\"/> -->\n\
+                <node TEXT=\"Roadmap\" ID=\"1\">\n\
+                <node TEXT=\"Parser &amp;todo\">\n<node TEXT='Lexer &amp;done'/>\n</node>\n\
+                <node ID=\"3\" TEXT=\"a &lt; b &#x26;&#38;prio:a1\"><icon BUILTIN=\"yes\"/></node>\n\
+                </node>\n</map>",
+                "((Roadmap(Parser &todo(Lexer &done))(a < b &&prio:a1)))",
+            ),
+            (
+                r#"<map><node TEXT="A"><node TEXT="B"/><node TEXT="C"/></node></map>"#,
+                "((A(B)(C)))",
+            ),
+            (r#"<map><node TEXT="line1&#xa;line2 &unknown;"/></map>"#, "((line1 line2 &unknown;))"),
+            (r#"<map><node><node TEXT=""/></node></map>"#, "((()))"),
+        ];
+
+        for (xml, exp) in scns {
+            let mut tree = Tree::new();
+            tree.init(xml)?;
+            assert_eq!(print(&tree), exp, "{xml}");
+            assert_eq!(
+                tree.content.split('\n').count(),
+                xml.split('\n').count(),
+                "{xml}"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_line_ix() -> util::Result<()> {
+        let xml = "<map>\n<node TEXT=\"A\">\n<node TEXT=\"B\"/><node TEXT=\"C\"/>\n</node>\n</map>";
+        let mut tree = Tree::new();
+        tree.init(xml)?;
+
+        let line_ixs = tree
+            .nodes
+            .iter()
+            .map(|node| node.line_ix)
+            .collect::<Vec<_>>();
+        assert_eq!(line_ixs, [0, 1, 2, 2]);
+        assert_eq!(tree.content, "\nA\nB C\n\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_errors() {
+        let scns = [
+            r#"<map><node TEXT="A"></map>"#,
+            r#"<map></node></map>"#,
+            r#"<map><node TEXT="A"#,
+            r#"<map><node TEXT=A/></map>"#,
+            r#"<map><!-- comment </map>"#,
+        ];
+
+        for xml in scns {
+            let mut tree = Tree::new();
+            assert!(tree.init(xml).is_err(), "{xml}");
+        }
+
+        // Nodes before the error are kept
+        let mut tree = Tree::new();
+        assert!(tree
+            .init(r#"<map><node TEXT="A &amp;todo"><node TEXT="B"#)
+            .is_err());
+        assert_eq!(print(&tree), "((A &todo))");
+    }
+}
@@ -0,0 +1,316 @@
+use crate::{amp, tree};
+
+// Reader for Org-mode files (`.org`): nesting follows the `*` depth of headings, body lines nest via their indentation.
+// Heading markup is translated into AMP:
+// - A `TODO` or `DONE` keyword becomes the Status `todo` or `done`
+// - Tags like `:bug:ui:` become `&bug &ui`
+// - `DEADLINE: <2024-10-01 Tue>` on the next line becomes `&due:2024-10-01`, `SCHEDULED:` becomes `&scheduled:`
+// `#+BEGIN_...`/`#+END_...` blocks are Data, all other text is Meta.
+#[derive(Default, Debug)]
+pub struct Tree {
+    pub nodes: Vec<Node>,
+}
+
+#[derive(Default, Debug)]
+pub struct Node {
+    pub line_ix: u64,
+    pub parts: Vec<Part>,
+    pub childs: Vec<usize>,
+    // Status name for a `TODO` or `DONE` keyword
+    pub status: Option<&'static str>,
+    // Paths for heading tags and planning timestamps
+    pub paths: Vec<amp::Path>,
+}
+
+type Part = tree::Part;
+type Range = std::ops::Range<usize>;
+
+impl Tree {
+    pub fn new() -> Tree {
+        Tree::default()
+    }
+
+    pub fn init(&mut self, content: &str) {
+        self.nodes.clear();
+        let root_ix = self.append(Node::default());
+
+        // Level and ix of the enclosing headings, the innermost last
+        let mut headings = Vec::<(usize, usize)>::new();
+        // Indentation and ix of the body Nodes that can still receive childs, the innermost last
+        let mut bodies = Vec::<(usize, usize)>::new();
+        // Heading from the previous line, its planning line is directly below
+        let mut prev_heading = None;
+        // Open `#+BEGIN_...` block
+        let mut block = None;
+
+        let mut offset = 0;
+        for (line_ix, line) in content.split_inclusive('\n').enumerate() {
+            let start = offset;
+            offset += line.len();
+
+            let text = line.trim_end();
+            let range = start..start + text.len();
+            let heading_ix = prev_heading.take();
+
+            if let Some(block_ix) = block {
+                let node: &mut Node = &mut self.nodes[block_ix];
+                node.parts.push(Part::new(&range, tree::Kind::Data));
+                if is_keyword(text, "#+end_") {
+                    block = None;
+                }
+                continue;
+            }
+
+            let indent = text.len() - text.trim_start().len();
+            if indent == text.len() {
+                // Blank lines do not result in a Node
+                continue;
+            }
+
+            let level = text.len() - text.trim_start_matches('*').len();
+            if level > 0 && text[level..].starts_with(' ') {
+                while headings.last().is_some_and(|(l, _)| *l >= level) {
+                    headings.pop();
+                }
+                bodies.clear();
+                let parent_ix = headings.last().map_or(root_ix, |(_, ix)| *ix);
+
+                let node = heading(text, start, level, line_ix as u64);
+                let ix = self.append(node);
+                self.nodes[parent_ix].childs.push(ix);
+                headings.push((level, ix));
+                prev_heading = Some(ix);
+                continue;
+            }
+
+            if let Some(heading_ix) = heading_ix {
+                let paths = planning(text);
+                if !paths.is_empty() {
+                    self.nodes[heading_ix].paths.extend(paths);
+                    continue;
+                }
+            }
+
+            while bodies.last().is_some_and(|(i, _)| *i >= indent) {
+                bodies.pop();
+            }
+            let parent_ix = bodies
+                .last()
+                .or(headings.last())
+                .map_or(root_ix, |(_, ix)| *ix);
+
+            let is_block = is_keyword(text.trim_start(), "#+begin_");
+            let kind = if is_block {
+                tree::Kind::Data
+            } else {
+                tree::Kind::Meta
+            };
+            let ix = self.append(Node {
+                line_ix: line_ix as u64,
+                parts: vec![Part::new(&(start + indent..range.end), kind)],
+                ..Default::default()
+            });
+            self.nodes[parent_ix].childs.push(ix);
+            if is_block {
+                block = Some(ix);
+            } else {
+                bodies.push((indent, ix));
+            }
+        }
+    }
+
+    fn append(&mut self, node: Node) -> usize {
+        let ix = self.nodes.len();
+        self.nodes.push(node);
+        ix
+    }
+}
+
+// Splits `** TODO Title :tag1:tag2:` into contiguous Parts, keeping only the title as Meta
+fn heading(text: &str, start: usize, level: usize, line_ix: u64) -> Node {
+    let mut node = Node {
+        line_ix,
+        ..Default::default()
+    };
+    node.parts
+        .push(Part::new(&(start..start + level), tree::Kind::Data));
+
+    let mut title = level..text.len();
+
+    // Keyword
+    let rest = text[title.clone()].trim_start();
+    let keyword_start = text.len() - rest.len();
+    let keyword = rest.split(' ').next().unwrap_or("");
+    node.status = match keyword {
+        "TODO" => Some("todo"),
+        "DONE" => Some("done"),
+        _ => None,
+    };
+    if node.status.is_some() {
+        title.start = keyword_start + keyword.len();
+        node.parts.push(Part::new(
+            &(start + level..start + title.start),
+            tree::Kind::Data,
+        ));
+    }
+
+    // Tags
+    let mut tags_part = None;
+    if let Some((head_len, tags)) = trailing_tags(&text[title.clone()]) {
+        for tag in tags.split(':') {
+            node.paths.push(amp::Path::new(false, false, &[tag]));
+        }
+        title.end = title.start + head_len;
+        tags_part = Some(Part::new(
+            &(start + title.end..start + text.len()),
+            tree::Kind::Data,
+        ));
+    }
+
+    node.parts
+        .push(Part::new(&shift(&title, start), tree::Kind::Meta));
+    node.parts.extend(tags_part);
+    node
+}
+
+// Length of `text` without its trailing tags like `   :bug:ui:`, and the tags without outer `:`
+fn trailing_tags(text: &str) -> Option<(usize, &str)> {
+    let (head, last) = text.rsplit_once([' ', '\t'])?;
+    let tags = last.strip_prefix(':')?.strip_suffix(':')?;
+    is_tags(tags).then_some((head.trim_end().len(), tags))
+}
+
+// End of the heading text in `line`, before its trailing tags, where AMP can be appended
+pub fn heading_end(line: &str) -> usize {
+    let level = line.len() - line.trim_start_matches('*').len();
+    if level > 0 && line[level..].starts_with(' ') {
+        if let Some((head_len, _)) = trailing_tags(&line[level..]) {
+            return level + head_len;
+        }
+    }
+    line.len()
+}
+
+// Paths for the `DEADLINE` and `SCHEDULED` timestamps in a planning line
+fn planning(text: &str) -> Vec<amp::Path> {
+    let text = text.trim_start();
+    let is_planning = ["DEADLINE:", "SCHEDULED:", "CLOSED:"]
+        .iter()
+        .any(|keyword| text.starts_with(keyword));
+    if !is_planning {
+        return Vec::new();
+    }
+
+    let mut paths = Vec::new();
+    for (keyword, key) in [("DEADLINE:", "due"), ("SCHEDULED:", "scheduled")] {
+        let date = text.split_once(keyword).and_then(|(_, rest)| {
+            let rest = rest.trim_start();
+            let rest = rest.strip_prefix('<').or(rest.strip_prefix('['))?;
+            rest.get(0..10)
+        });
+        if let Some(date) = date.filter(|date| amp::Date::try_from(*date).is_ok()) {
+            paths.push(amp::Path::new(false, false, &[key, date]));
+        }
+    }
+    paths
+}
+
+// Org-mode tags consist of letters, digits, `_`, `@`, `#` and `%`, separated by `:`
+fn is_tags(tags: &str) -> bool {
+    tags.split(':').all(|tag| {
+        !tag.is_empty()
+            && tag
+                .chars()
+                .all(|ch| ch.is_alphanumeric() || "_@#%".contains(ch))
+    })
+}
+
+fn is_keyword(text: &str, keyword: &str) -> bool {
+    text.get(0..keyword.len())
+        .is_some_and(|s| s.eq_ignore_ascii_case(keyword))
+}
+
+fn shift(range: &Range, offset: usize) -> Range {
+    range.start + offset..range.end + offset
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tree::test_util;
+
+    fn print(tree: &Tree, content: &str) -> String {
+        test_util::print(
+            &tree.nodes,
+            |node| &node.childs,
+            |node, s| {
+                if let Some(status) = node.status {
+                    s.push_str(&format!("[{status}]"));
+                }
+                for path in &node.paths {
+                    s.push_str(&format!("[{path}]"));
+                }
+                for part in &node.parts {
+                    s.push_str(match part.kind {
+                        tree::Kind::Meta => "M",
+                        tree::Kind::Data => "D",
+                    });
+                    if let Some(text) = content.get(part.range.clone()) {
+                        s.push_str(text);
+                    }
+                }
+            },
+        )
+    }
+
+    #[test]
+    fn test_tree() {
+        let scns = [
+            ("", "()"),
+            ("* Title", "((D*M Title))"),
+            ("*bold* text", "((M*bold* text))"),
+            ("* A\n** B\n* C", "((D*M A(D**M B))(D*M C))"),
+            ("* A\n*** B\n** C", "((D*M A(D***M B)(D**M C)))"),
+            (
+                "* A\ntext\n- a\n  - b\n- c",
+                "((D*M A(Mtext)(M- a(M- b))(M- c)))",
+            ),
+            ("intro\n* A", "((Mintro)(D*M A))"),
+            // Keywords
+            (
+                "* TODO Write &prio:a1",
+                "(([todo]D*D TODOM Write &prio:a1))",
+            ),
+            ("** DONE Release", "(([done]D**D DONEM Release))"),
+            ("* TODOS", "((D*M TODOS))"),
+            // Tags
+            (
+                "* TODO Fix parser   :bug:ui:",
+                "(([todo][bug][ui]D*D TODOM Fix parserD   :bug:ui:))",
+            ),
+            ("* Time 10:30:", "((D*M Time 10:30:))"),
+            ("* A :a b:", "((D*M A :a b:))"),
+            // Planning
+            (
+                "* TODO A\n  DEADLINE: <2024-10-01 Tue> SCHEDULED: <2024-09-20 Fri>\nbody",
+                "(([todo][due:2024-10-01][scheduled:2024-09-20]D*D TODOM A(Mbody)))",
+            ),
+            (
+                "* A\nbody\nDEADLINE: <2024-10-01>",
+                "((D*M A(Mbody)(MDEADLINE: <2024-10-01>)))",
+            ),
+            // Blocks
+            (
+                "* A\n#+BEGIN_SRC rust\n// &todo\n#+end_src\nafter",
+                "((D*M A(D#+BEGIN_SRC rustD// &todoD#+end_src)(Mafter)))",
+            ),
+        ];
+
+        for (content, exp) in scns {
+            let mut tree = Tree::new();
+            tree.init(content);
+            assert_eq!(print(&tree, content), exp, "{content:?}");
+        }
+    }
+}
@@ -0,0 +1,67 @@
+// Helpers shared by the tests of the parsing Trees and the Forest
+use crate::tree::{Forest, Node, Part};
+
+// Formats a parsing Tree as nested parentheses, starting from the root at ix 0, eg `((a(b))(c))`
+// `label` appends the text of a single Node, eg via `push_parts()`
+pub fn print<N>(
+    nodes: &[N],
+    childs: impl Fn(&N) -> &[usize],
+    label: impl Fn(&N, &mut String),
+) -> String {
+    let mut s = String::new();
+    print_(nodes, &nodes[0], &childs, &label, &mut s);
+    s
+}
+fn print_<N>(
+    nodes: &[N],
+    node: &N,
+    childs: &impl Fn(&N) -> &[usize],
+    label: &impl Fn(&N, &mut String),
+    s: &mut String,
+) {
+    s.push('(');
+    label(node, s);
+    for &child_ix in childs(node) {
+        print_(nodes, &nodes[child_ix], childs, label, s);
+    }
+    s.push(')');
+}
+
+// Appends the text of each Part
+pub fn push_parts(parts: &[Part], content: &str, s: &mut String) {
+    for part in parts {
+        if let Some(text) = content.get(part.range.clone()) {
+            s.push_str(text);
+        }
+    }
+}
+
+// Node.org of all Nodes that have one, in the order of Forest::each_node
+pub fn orgs(forest: &Forest) -> Vec<String> {
+    let mut orgs = Vec::new();
+    forest
+        .each_node(|_tree, node| {
+            if !node.org.is_empty() {
+                orgs.push(node.org.to_string());
+            }
+            Ok(())
+        })
+        .unwrap();
+    orgs
+}
+
+// First Node whose content contains `needle`
+pub fn find<'a>(forest: &'a Forest, needle: &str) -> Option<&'a Node> {
+    forest.trees.iter().find_map(|tree| {
+        tree.nodes
+            .iter()
+            .find(|node| node.content(tree).contains(needle))
+    })
+}
+
+// Single-letter name of an item like `- [x] A &todo`, other content has no name
+pub fn name(content: &str) -> Option<&str> {
+    let name = content.trim_start_matches(['-', ' ', '[', ']', 'x']);
+    (name.get(1..2) == Some(" ") && name.starts_with(|ch: char| ch.is_ascii_uppercase()))
+        .then(|| &name[0..1])
+}